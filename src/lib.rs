@@ -5,19 +5,34 @@ mod span;
 use std::backtrace::{Backtrace, BacktraceStatus};
 
 pub use parser::{Expr, ParseError, ParseErrorKind};
-pub use rt::{IntoExtFunc, Program, Registry, RuntimeError, Value};
+pub use rt::{
+    ArithmeticMode, Column, Context, ExternalError, IntoExtFunc, IntoFnResult, LocalFn, Param,
+    Program, Registry, RegistryError, RuntimeError, RuntimeErrorKind, SharedRegistry, Signature,
+    TryFromValue, Type, TypeError, TypeErrorKind, Value, VarHandle, VarRef, Variable,
+};
 pub use span::Span;
 
-pub fn eval_with_registry(registry: &mut Registry, source: &str) -> Result<Value, Error> {
+pub fn eval_with_context<L: LocalFn>(
+    registry: &Registry<L>,
+    ctx: &Context,
+    source: &str,
+) -> Result<Value, Error> {
     let expr = Expr::from_src(source.as_bytes())?;
     let program = Program::compile(registry, &expr)?;
-    let result = program.run(registry)?;
+    let result = program.run(registry, ctx)?;
     Ok(result)
 }
 
+pub fn eval_with_registry<L: LocalFn>(
+    registry: &Registry<L>,
+    source: &str,
+) -> Result<Value, Error> {
+    eval_with_context(registry, &Context::new(registry), source)
+}
+
 pub fn eval(source: &str) -> Result<Value, Error> {
-    let mut registry = Registry::default();
-    eval_with_registry(&mut registry, source)
+    let registry = Registry::default();
+    eval_with_registry(&registry, source)
 }
 
 #[derive(Debug)]
@@ -7,10 +7,16 @@ pub enum ParseErrorKind {
     UnexpectedEOF,
     ExpectingButGotEOF(TokenKind),
     UnexpectedChar(char),
+    UnterminatedString,
+    InvalidEscape(char),
     UnexpectedPrimaryExpr(TokenKind),
     UnexpectedTokenAtEOF(TokenKind),
     Expecting(TokenKind, TokenKind),
+    ExpectingKeyword(&'static str, TokenKind),
     InvalidFunctionCall,
+    WrongArgumentCount(u32, u32),
+    /// A function or lambda names the same parameter twice.
+    DuplicateParameter(String),
     ParseFloatError(std::num::ParseFloatError),
     ParseIntError(std::num::ParseIntError),
 }
@@ -39,6 +45,10 @@ impl ParseError {
         }
     }
 
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
     pub fn span(&self) -> Option<Span> {
         self.span
     }
@@ -60,6 +70,10 @@ impl Display for ParseError {
             ParseErrorKind::UnexpectedChar(c) => {
                 write!(f, "Unexpected character '{c}' appear in expression")
             }
+            ParseErrorKind::UnterminatedString => write!(f, "Missing closing quote of string"),
+            ParseErrorKind::InvalidEscape(c) => {
+                write!(f, "Invalid escape sequence '\\{c}' in string")
+            }
             ParseErrorKind::UnexpectedPrimaryExpr(tk) => {
                 write!(f, "Expecting an expression but got '{}'", tk.to_char())
             }
@@ -72,9 +86,20 @@ impl Display for ParseError {
                 ex.to_char(),
                 got.to_char()
             ),
+            ParseErrorKind::ExpectingKeyword(ex, got) => {
+                write!(f, "Expecting '{}' but got '{}'", ex, got.to_char())
+            }
             ParseErrorKind::InvalidFunctionCall => {
                 write!(f, "This is not a valid function call")
             }
+            ParseErrorKind::WrongArgumentCount(expected, got) => write!(
+                f,
+                "Function called with wrong number of arguments (expected: {}, got: {})",
+                expected, got
+            ),
+            ParseErrorKind::DuplicateParameter(name) => {
+                write!(f, "Parameter {name} is declared twice")
+            }
             ParseErrorKind::ParseFloatError(err) => write!(f, "Parse float error: {err}"),
             ParseErrorKind::ParseIntError(err) => write!(f, "Parse int error: {err}"),
         }
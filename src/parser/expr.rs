@@ -7,19 +7,31 @@ pub enum BinaryOp {
     Mul,
     Div,
     Mod,
+    Pow,
     Equal,
     NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
     LogicalAnd,
     LogicalOr,
+    NullCoalesce,
     BitAnd,
     BitOr,
     BitXor,
+    ShiftLeft,
+    ShiftRight,
+    UnsignedShiftRight,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum UnaryOp {
     Neg,
+    /// Logical not `!`.
     Not,
+    /// Bitwise complement `~`.
+    BitNot,
 }
 
 #[derive(Debug)]
@@ -29,6 +41,18 @@ pub enum Expr {
     BinaryOp(Box<Expr>, BinaryOp, Box<Expr>, Span),
     UnaryOp(UnaryOp, Box<Expr>, Span),
     Call(Box<[u8]>, Vec<Expr>, Span),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>, Span),
+    List(Vec<Expr>, Span),
+    Index(Box<Expr>, Box<Expr>, Span),
+    Member(Box<Expr>, Box<[u8]>, Span),
+    /// `let name = value in body`, `name` is only visible in `body`.
+    Let(Box<[u8]>, Box<Expr>, Box<Expr>, Span),
+    /// `fn name(params) = body; rest`, the function is visible in `rest` but
+    /// not in its own body.
+    Function(Box<[u8]>, Vec<Box<[u8]>>, Box<Expr>, Box<Expr>, Span),
+    /// `|params| body`, only valid as an argument of `map`, `filter`,
+    /// `reduce`, `any` and `all`.
+    Lambda(Vec<Box<[u8]>>, Box<Expr>, Span),
 }
 
 impl Expr {
@@ -39,6 +63,117 @@ impl Expr {
             Expr::BinaryOp(_, _, _, s) => *s,
             Expr::UnaryOp(_, _, s) => *s,
             Expr::Call(_, _, s) => *s,
+            Expr::Conditional(_, _, _, s) => *s,
+            Expr::List(_, s) => *s,
+            Expr::Index(_, _, s) => *s,
+            Expr::Member(_, _, s) => *s,
+            Expr::Let(_, _, _, s) => *s,
+            Expr::Function(_, _, _, _, s) => *s,
+            Expr::Lambda(_, _, s) => *s,
         }
     }
 }
+
+impl BinaryOp {
+    pub fn to_char(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Pow => "**",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::LogicalAnd => "&&",
+            BinaryOp::LogicalOr => "||",
+            BinaryOp::NullCoalesce => "??",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+            BinaryOp::UnsignedShiftRight => ">>>",
+        }
+    }
+}
+
+impl UnaryOp {
+    pub fn to_char(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+        }
+    }
+}
+
+/// Prints the expression back as source with every operation fully
+/// parenthesized, which makes the parsed grouping explicit.
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Literal(Value::Str(v), _) => write!(f, "\"{}\"", v.escape_debug()),
+            Expr::Literal(v, _) => write!(f, "{v}"),
+            Expr::Identifier(ident, _) => write!(f, "{}", String::from_utf8_lossy(ident)),
+            Expr::BinaryOp(a, op, b, _) => write!(f, "({a} {} {b})", op.to_char()),
+            Expr::UnaryOp(op, expr, _) => write!(f, "({}{expr})", op.to_char()),
+            Expr::Call(ident, args, _) => {
+                write!(f, "{}(", String::from_utf8_lossy(ident))?;
+                write_comma_separated(f, args)?;
+                write!(f, ")")
+            }
+            Expr::Conditional(cond, a, b, _) => write!(f, "({cond} ? {a} : {b})"),
+            Expr::List(items, _) => {
+                write!(f, "[")?;
+                write_comma_separated(f, items)?;
+                write!(f, "]")
+            }
+            Expr::Index(expr, index, _) => write!(f, "{expr}[{index}]"),
+            Expr::Member(expr, field, _) => {
+                write!(f, "{expr}.{}", String::from_utf8_lossy(field))
+            }
+            Expr::Let(name, value, body, _) => write!(
+                f,
+                "(let {} = {value} in {body})",
+                String::from_utf8_lossy(name)
+            ),
+            Expr::Function(name, params, body, rest, _) => {
+                write!(f, "(fn {}(", String::from_utf8_lossy(name))?;
+                write_params(f, params)?;
+                write!(f, ") = {body}; {rest})")
+            }
+            Expr::Lambda(params, body, _) => {
+                write!(f, "(|")?;
+                write_params(f, params)?;
+                write!(f, "| {body})")
+            }
+        }
+    }
+}
+
+fn write_params(f: &mut std::fmt::Formatter<'_>, params: &[Box<[u8]>]) -> std::fmt::Result {
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", String::from_utf8_lossy(param))?;
+    }
+
+    Ok(())
+}
+
+fn write_comma_separated(f: &mut std::fmt::Formatter<'_>, exprs: &[Expr]) -> std::fmt::Result {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{expr}")?;
+    }
+
+    Ok(())
+}
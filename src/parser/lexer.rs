@@ -8,44 +8,76 @@ pub enum TokenKind {
     Plus,
     Minus,
     Asterisk,
+    AsteriskAsterisk,
     Slash,
     Percent,
     Comma,
+    Semicolon,
     Period,
+    QuestionMark,
+    QuestionQuestion,
+    Colon,
     Caret,
     Ampersand,
     AmpersandAmpersand,
     Pipe,
     PipePipe,
+    Equal,
     EqualEqual,
     ExclamationEqual,
     ExclamationMark,
+    Tilde,
+    Less,
+    LessEqual,
+    LessLess,
+    Greater,
+    GreaterEqual,
+    GreaterGreater,
+    GreaterGreaterGreater,
     OpenParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
 }
 
 impl TokenKind {
-    pub fn to_char(&self) -> &'static str {
+    pub fn to_char(self) -> &'static str {
         match self {
             TokenKind::Literal => "<literal>",
             TokenKind::Identifier => "<ident>",
             TokenKind::Plus => "+",
             TokenKind::Minus => "-",
             TokenKind::Asterisk => "*",
+            TokenKind::AsteriskAsterisk => "**",
             TokenKind::Slash => "/",
             TokenKind::Percent => "%",
             TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
             TokenKind::Period => ".",
+            TokenKind::QuestionMark => "?",
+            TokenKind::QuestionQuestion => "??",
+            TokenKind::Colon => ":",
             TokenKind::Caret => "^",
             TokenKind::Ampersand => "&",
             TokenKind::AmpersandAmpersand => "&&",
             TokenKind::Pipe => "|",
             TokenKind::PipePipe => "||",
+            TokenKind::Equal => "=",
             TokenKind::EqualEqual => "==",
             TokenKind::ExclamationEqual => "!=",
             TokenKind::ExclamationMark => "!",
+            TokenKind::Tilde => "~",
+            TokenKind::Less => "<",
+            TokenKind::LessEqual => "<=",
+            TokenKind::LessLess => "<<",
+            TokenKind::Greater => ">",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::GreaterGreater => ">>",
+            TokenKind::GreaterGreaterGreater => ">>>",
             TokenKind::OpenParen => "(",
             TokenKind::CloseParen => ")",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
         }
     }
 }
@@ -63,6 +95,7 @@ pub enum LexValue<'a> {
     Int(i64),
     Float(f64),
     Identifier(&'a [u8]),
+    Str(String),
 }
 
 pub fn lex<'a>(str: &'a [u8]) -> Result<Vec<Token<'a>>, ParseError> {
@@ -101,28 +134,56 @@ pub fn lex<'a>(str: &'a [u8]) -> Result<Vec<Token<'a>>, ParseError> {
 
                 TokenKind::Identifier
             }
+            b'"' | b'\'' => {
+                let (value, end) = lex_string(str, start_pos)?;
+                pos = end;
+                tokens.push(Token {
+                    kind: TokenKind::Literal,
+                    span: Span {
+                        from: start_pos,
+                        to: pos - 1,
+                    },
+                    value: LexValue::Str(value),
+                });
+
+                continue;
+            }
             b'+' => TokenKind::Plus,
             b'-' => TokenKind::Minus,
-            b'*' => TokenKind::Asterisk,
+            b'*' => {
+                if pos < str.len() && str[pos] == b'*' {
+                    pos += 1;
+                    TokenKind::AsteriskAsterisk
+                } else {
+                    TokenKind::Asterisk
+                }
+            }
             b'/' => TokenKind::Slash,
             b'%' => TokenKind::Percent,
             b'^' => TokenKind::Caret,
+            b'~' => TokenKind::Tilde,
             b'.' => TokenKind::Period,
+            b'?' => {
+                if pos < str.len() && str[pos] == b'?' {
+                    pos += 1;
+                    TokenKind::QuestionQuestion
+                } else {
+                    TokenKind::QuestionMark
+                }
+            }
+            b':' => TokenKind::Colon,
             b',' => TokenKind::Comma,
+            b';' => TokenKind::Semicolon,
             b'(' => TokenKind::OpenParen,
             b')' => TokenKind::CloseParen,
+            b'[' => TokenKind::OpenBracket,
+            b']' => TokenKind::CloseBracket,
             b'=' => {
                 if pos < str.len() && str[pos] == b'=' {
                     pos += 1;
                     TokenKind::EqualEqual
                 } else {
-                    return Err(ParseError::new(
-                        ParseErrorKind::UnexpectedChar(c as char),
-                        Span {
-                            from: start_pos,
-                            to: pos - 1,
-                        },
-                    ));
+                    TokenKind::Equal
                 }
             }
             b'!' => {
@@ -133,6 +194,32 @@ pub fn lex<'a>(str: &'a [u8]) -> Result<Vec<Token<'a>>, ParseError> {
                     TokenKind::ExclamationMark
                 }
             }
+            b'<' => match str.get(pos) {
+                Some(b'=') => {
+                    pos += 1;
+                    TokenKind::LessEqual
+                }
+                Some(b'<') => {
+                    pos += 1;
+                    TokenKind::LessLess
+                }
+                _ => TokenKind::Less,
+            },
+            b'>' => match str.get(pos..) {
+                Some([b'=', ..]) => {
+                    pos += 1;
+                    TokenKind::GreaterEqual
+                }
+                Some([b'>', b'>', ..]) => {
+                    pos += 2;
+                    TokenKind::GreaterGreaterGreater
+                }
+                Some([b'>', ..]) => {
+                    pos += 1;
+                    TokenKind::GreaterGreater
+                }
+                _ => TokenKind::Greater,
+            },
             b'&' => {
                 if pos < str.len() && str[pos] == b'&' {
                     pos += 1;
@@ -143,6 +230,7 @@ pub fn lex<'a>(str: &'a [u8]) -> Result<Vec<Token<'a>>, ParseError> {
             }
             b'|' => {
                 if pos < str.len() && str[pos] == b'|' {
+                    pos += 1;
                     TokenKind::PipePipe
                 } else {
                     TokenKind::Pipe
@@ -207,3 +295,77 @@ pub fn lex<'a>(str: &'a [u8]) -> Result<Vec<Token<'a>>, ParseError> {
 
     Ok(tokens)
 }
+
+/// Lex a string literal opening with the quote at `start`, returns the unescaped
+/// string and the position right after the closing quote.
+fn lex_string(str: &[u8], start: usize) -> Result<(String, usize), ParseError> {
+    let quote = str[start];
+    let mut bytes = Vec::new();
+    let mut pos = start + 1;
+    loop {
+        let Some(&c) = str.get(pos) else {
+            return Err(ParseError::new(
+                ParseErrorKind::UnterminatedString,
+                Span {
+                    from: start,
+                    to: str.len() - 1,
+                },
+            ));
+        };
+        pos += 1;
+
+        if c == quote {
+            break;
+        }
+
+        if c != b'\\' {
+            bytes.push(c);
+            continue;
+        }
+
+        let escape_span = Span {
+            from: pos - 1,
+            to: pos,
+        };
+        let Some(&escaped) = str.get(pos) else {
+            continue;
+        };
+        pos += 1;
+
+        match escaped {
+            b'n' => bytes.push(b'\n'),
+            b'r' => bytes.push(b'\r'),
+            b't' => bytes.push(b'\t'),
+            b'0' => bytes.push(b'\0'),
+            b'\\' | b'"' | b'\'' => bytes.push(escaped),
+            b'u' => {
+                let code = str
+                    .get(pos..)
+                    .and_then(|rest| rest.strip_prefix(b"{"))
+                    .and_then(|rest| rest.iter().position(|&c| c == b'}').map(|end| &rest[..end]));
+                let ch = code
+                    .and_then(|code| core::str::from_utf8(code).ok())
+                    .and_then(|code| u32::from_str_radix(code, 16).ok())
+                    .and_then(char::from_u32);
+
+                let (Some(code), Some(ch)) = (code, ch) else {
+                    return Err(ParseError::new(
+                        ParseErrorKind::InvalidEscape('u'),
+                        escape_span,
+                    ));
+                };
+
+                pos += code.len() + 2;
+                bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            _ => {
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidEscape(escaped as char),
+                    escape_span,
+                ));
+            }
+        }
+    }
+
+    Ok((String::from_utf8_lossy(&bytes).into_owned(), pos))
+}
@@ -8,6 +8,7 @@ pub use self::{
 };
 
 use self::lexer::{lex, LexValue, Token, TokenKind};
+use crate::{Span, Value};
 
 struct Parser<'a> {
     tokens: &'a [Token<'a>],
@@ -36,13 +37,13 @@ impl<'a> Parser<'a> {
             return Err(ParseError::new_nospan(ParseErrorKind::UnexpectedEOF));
         };
 
-        let precedent = self.operator_precedent(tk.kind).unwrap_or(0);
         let expr = match tk.kind {
             TokenKind::Literal => {
                 self.skip()?;
                 match tk.value {
                     LexValue::Float(v) => Expr::Literal(v.into(), tk.span),
                     LexValue::Int(v) => Expr::Literal(v.into(), tk.span),
+                    LexValue::Str(ref v) => Expr::Literal(v.as_str().into(), tk.span),
                     _ => unreachable!(),
                 }
             }
@@ -52,6 +53,9 @@ impl<'a> Parser<'a> {
                     LexValue::Identifier(ident) => match ident {
                         b"true" => Expr::Literal(true.into(), tk.span),
                         b"false" => Expr::Literal(false.into(), tk.span),
+                        b"null" => Expr::Literal(Value::Null, tk.span),
+                        b"let" => self.parse_let(tk.span)?,
+                        b"fn" => self.parse_function(tk.span)?,
                         ident => Expr::Identifier(Box::from(ident), tk.span),
                     },
                     _ => unreachable!(),
@@ -59,18 +63,39 @@ impl<'a> Parser<'a> {
             }
             TokenKind::OpenParen => {
                 self.skip()?;
-                let expr = self.parse_expr(precedent)?;
+                let expr = self.parse_expr(0)?;
                 self.consume(TokenKind::CloseParen)?;
                 expr
             }
+            TokenKind::OpenBracket => {
+                self.skip()?;
+                let items = self.parse_comma_separated(TokenKind::CloseBracket)?;
+                Expr::List(items, tk.span)
+            }
+            TokenKind::Pipe => {
+                self.skip()?;
+                let params = self.parse_params(TokenKind::Pipe)?;
+                let body = self.parse_expr(0)?;
+                Expr::Lambda(params, Box::new(body), tk.span)
+            }
+            TokenKind::PipePipe => {
+                self.skip()?;
+                let body = self.parse_expr(0)?;
+                Expr::Lambda(Vec::new(), Box::new(body), tk.span)
+            }
             TokenKind::ExclamationMark => {
                 self.skip()?;
-                let expr = self.parse_expr(precedent)?;
+                let expr = self.parse_expr(Self::UNARY_PRECEDENT)?;
                 Expr::UnaryOp(UnaryOp::Not, Box::new(expr), tk.span)
             }
+            TokenKind::Tilde => {
+                self.skip()?;
+                let expr = self.parse_expr(Self::UNARY_PRECEDENT)?;
+                Expr::UnaryOp(UnaryOp::BitNot, Box::new(expr), tk.span)
+            }
             TokenKind::Minus => {
                 self.skip()?;
-                let expr = self.parse_expr(precedent)?;
+                let expr = self.parse_expr(Self::UNARY_PRECEDENT)?;
                 Expr::UnaryOp(UnaryOp::Neg, Box::new(expr), tk.span)
             }
             _ => {
@@ -117,6 +142,17 @@ impl<'a> Parser<'a> {
                     tk.span,
                 )
             }
+            TokenKind::AsteriskAsterisk => {
+                self.skip()?;
+                // Parse the exponent one level lower so that `**` groups to
+                // the right
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::Pow,
+                    Box::new(self.parse_expr(min_precedent - 1)?),
+                    tk.span,
+                )
+            }
             TokenKind::Slash => {
                 self.skip()?;
                 Expr::BinaryOp(
@@ -162,6 +198,33 @@ impl<'a> Parser<'a> {
                     tk.span,
                 )
             }
+            TokenKind::LessLess => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::ShiftLeft,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
+            TokenKind::GreaterGreater => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::ShiftRight,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
+            TokenKind::GreaterGreaterGreater => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::UnsignedShiftRight,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
             TokenKind::AmpersandAmpersand => {
                 self.skip()?;
                 Expr::BinaryOp(
@@ -180,6 +243,15 @@ impl<'a> Parser<'a> {
                     tk.span,
                 )
             }
+            TokenKind::QuestionQuestion => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::NullCoalesce,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
             TokenKind::EqualEqual => {
                 self.skip()?;
                 Expr::BinaryOp(
@@ -198,28 +270,67 @@ impl<'a> Parser<'a> {
                     tk.span,
                 )
             }
+            TokenKind::Less => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::Less,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
+            TokenKind::LessEqual => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::LessEqual,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
+            TokenKind::Greater => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::Greater,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
+            TokenKind::GreaterEqual => {
+                self.skip()?;
+                Expr::BinaryOp(
+                    Box::new(lhs),
+                    BinaryOp::GreaterEqual,
+                    Box::new(self.parse_expr(min_precedent)?),
+                    tk.span,
+                )
+            }
+            TokenKind::QuestionMark => {
+                self.skip()?;
+                let a = self.parse_expr(0)?;
+                self.consume(TokenKind::Colon)?;
+                // Parse the else branch one level lower so that nested
+                // conditionals group to the right
+                let b = self.parse_expr(min_precedent - 1)?;
+                Expr::Conditional(Box::new(lhs), Box::new(a), Box::new(b), tk.span)
+            }
             TokenKind::OpenParen => {
                 if let Expr::Identifier(ident, span) = lhs {
                     self.skip()?;
-                    let mut args = Vec::new();
-                    while let Some(tk) = self.peek() {
-                        if tk.kind == TokenKind::CloseParen {
-                            break;
-                        }
-
-                        args.push(self.parse_expr(0)?);
-                        if let Some(tk) = self.peek() {
-                            if tk.kind == TokenKind::Comma {
-                                self.skip()?;
-                                continue;
-                            }
-
-                            break;
-                        }
-                    }
+                    let args = self.parse_comma_separated(TokenKind::CloseParen)?;
+                    if ident.as_ref() == b"if" {
+                        let [cond, a, b]: [Expr; 3] = args.try_into().map_err(|args: Vec<_>| {
+                            ParseError::new(
+                                ParseErrorKind::WrongArgumentCount(3, args.len() as u32),
+                                span,
+                            )
+                        })?;
 
-                    self.consume(TokenKind::CloseParen)?;
-                    Expr::Call(ident, args, span)
+                        Expr::Conditional(Box::new(cond), Box::new(a), Box::new(b), span)
+                    } else {
+                        Expr::Call(ident, args, span)
+                    }
                 } else {
                     return Err(ParseError::new(
                         ParseErrorKind::InvalidFunctionCall,
@@ -227,29 +338,141 @@ impl<'a> Parser<'a> {
                     ));
                 }
             }
+            TokenKind::OpenBracket => {
+                self.skip()?;
+                let index = self.parse_expr(0)?;
+                self.consume(TokenKind::CloseBracket)?;
+                Expr::Index(Box::new(lhs), Box::new(index), tk.span)
+            }
+            TokenKind::Period => {
+                self.skip()?;
+                let field = self.consume_identifier()?;
+                Expr::Member(Box::new(lhs), Box::from(field), tk.span)
+            }
             _ => unreachable!("{:?}", tk),
         })
     }
 
+    /// Parse `name = value in body` following `let`, the body extends as far
+    /// as possible.
+    fn parse_let(&mut self, span: Span) -> Result<Expr, ParseError> {
+        let name = self.consume_identifier()?;
+        self.consume(TokenKind::Equal)?;
+        let value = self.parse_expr(0)?;
+        self.consume_keyword("in")?;
+        let body = self.parse_expr(0)?;
+        Ok(Expr::Let(
+            Box::from(name),
+            Box::new(value),
+            Box::new(body),
+            span,
+        ))
+    }
+
+    /// Parse `name(params) = body; rest` following `fn`.
+    fn parse_function(&mut self, span: Span) -> Result<Expr, ParseError> {
+        let name = self.consume_identifier()?;
+        self.consume(TokenKind::OpenParen)?;
+        let params = self.parse_params(TokenKind::CloseParen)?;
+        self.consume(TokenKind::Equal)?;
+        let body = self.parse_expr(0)?;
+        self.consume(TokenKind::Semicolon)?;
+        let rest = self.parse_expr(0)?;
+        Ok(Expr::Function(
+            Box::from(name),
+            params,
+            Box::new(body),
+            Box::new(rest),
+            span,
+        ))
+    }
+
+    /// Parse comma separated parameter names up to and including `close`.
+    fn parse_params(&mut self, close: TokenKind) -> Result<Vec<Box<[u8]>>, ParseError> {
+        let mut params: Vec<Box<[u8]>> = Vec::new();
+        while let Some(tk) = self.peek().filter(|tk| tk.kind != close) {
+            let param = self.consume_identifier()?;
+            if params.iter().any(|p| p.as_ref() == param) {
+                return Err(ParseError::new(
+                    ParseErrorKind::DuplicateParameter(String::from_utf8_lossy(param).to_string()),
+                    tk.span,
+                ));
+            }
+
+            params.push(Box::from(param));
+            if self.peek().is_some_and(|tk| tk.kind == TokenKind::Comma) {
+                self.skip()?;
+            } else {
+                break;
+            }
+        }
+
+        self.consume(close)?;
+        Ok(params)
+    }
+
+    /// Parse a comma separated list of expressions up to and including `close`.
+    fn parse_comma_separated(&mut self, close: TokenKind) -> Result<Vec<Expr>, ParseError> {
+        let mut exprs = Vec::new();
+        while let Some(tk) = self.peek() {
+            if tk.kind == close {
+                break;
+            }
+
+            exprs.push(self.parse_expr(0)?);
+            if let Some(tk) = self.peek() {
+                if tk.kind == TokenKind::Comma {
+                    self.skip()?;
+                    continue;
+                }
+
+                break;
+            }
+        }
+
+        self.consume(close)?;
+        Ok(exprs)
+    }
+
+    /// Binding power of the prefix operators `!`, `~` and `-`, tighter than every
+    /// binary operator but `**`, and looser than a call, an index or a member
+    /// access. `-2 ** 2` is `-(2 ** 2)`.
+    const UNARY_PRECEDENT: i32 = 17;
+
+    /// Binding power of infix and postfix operators, following C. Every binary
+    /// operator is left associative but `**`, which is right associative like
+    /// the conditional `?:`.
     fn operator_precedent(&self, kind: TokenKind) -> Option<i32> {
         match kind {
-            TokenKind::OpenParen => Some(20),
-            TokenKind::ExclamationMark => Some(17),
+            TokenKind::OpenParen | TokenKind::OpenBracket | TokenKind::Period => Some(20),
+            TokenKind::AsteriskAsterisk => Some(18),
             TokenKind::Asterisk | TokenKind::Percent | TokenKind::Slash => Some(12),
             TokenKind::Plus | TokenKind::Minus => Some(11),
+            TokenKind::LessLess | TokenKind::GreaterGreater | TokenKind::GreaterGreaterGreater => {
+                Some(10)
+            }
+            TokenKind::Less
+            | TokenKind::LessEqual
+            | TokenKind::Greater
+            | TokenKind::GreaterEqual => Some(9),
+            TokenKind::EqualEqual | TokenKind::ExclamationEqual => Some(8),
             TokenKind::Ampersand => Some(7),
             TokenKind::Caret => Some(6),
             TokenKind::Pipe => Some(5),
+            TokenKind::AmpersandAmpersand => Some(4),
+            TokenKind::PipePipe => Some(3),
+            TokenKind::QuestionQuestion => Some(2),
+            TokenKind::QuestionMark => Some(1),
             _ => None,
         }
     }
 
     fn peek(&self) -> Option<&'a Token<'a>> {
-        self.tokens.get(0)
+        self.tokens.first()
     }
 
     fn consume(&mut self, kind: TokenKind) -> Result<(), ParseError> {
-        if self.tokens.len() == 0 {
+        if self.tokens.is_empty() {
             return Err(ParseError::new_nospan(ParseErrorKind::ExpectingButGotEOF(
                 kind,
             )));
@@ -266,8 +489,41 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    fn consume_identifier(&mut self) -> Result<&'a [u8], ParseError> {
+        let Some(tk) = self.peek() else {
+            return Err(ParseError::new_nospan(ParseErrorKind::ExpectingButGotEOF(
+                TokenKind::Identifier,
+            )));
+        };
+
+        match tk.value {
+            LexValue::Identifier(ident) => {
+                self.skip()?;
+                Ok(ident)
+            }
+            _ => Err(ParseError::new(
+                ParseErrorKind::Expecting(TokenKind::Identifier, tk.kind),
+                tk.span,
+            )),
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &'static str) -> Result<(), ParseError> {
+        let Some(tk) = self.peek() else {
+            return Err(ParseError::new_nospan(ParseErrorKind::UnexpectedEOF));
+        };
+
+        match tk.value {
+            LexValue::Identifier(ident) if ident == keyword.as_bytes() => self.skip(),
+            _ => Err(ParseError::new(
+                ParseErrorKind::ExpectingKeyword(keyword, tk.kind),
+                tk.span,
+            )),
+        }
+    }
+
     fn skip(&mut self) -> Result<(), ParseError> {
-        if self.tokens.len() == 0 {
+        if self.tokens.is_empty() {
             return Err(ParseError::new_nospan(ParseErrorKind::UnexpectedEOF));
         }
 
@@ -282,7 +538,7 @@ impl Expr {
 
         let mut parser = Parser { tokens: &tokens };
         let expr = parser.parse_expr(0)?;
-        if let Some(tk) = parser.tokens.get(0) {
+        if let Some(tk) = parser.tokens.first() {
             return Err(ParseError::new(
                 ParseErrorKind::UnexpectedTokenAtEOF(tk.kind),
                 tk.span,
@@ -0,0 +1,406 @@
+use crate::parser::{BinaryOp, UnaryOp};
+
+use super::{
+    call_error, ix::Instruction, ArithmeticMode, Context, LocalFn, Program, Registry,
+    RegistryError, RuntimeError, RuntimeErrorKind, Type, Value, VarHandle,
+};
+
+/// Values of one variable for every row of a batch.
+#[derive(Debug, Clone, Copy)]
+pub enum Column<'a> {
+    Int(&'a [i64]),
+    Float(&'a [f64]),
+    Boolean(&'a [bool]),
+    Value(&'a [Value]),
+}
+
+impl Column<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Int(v) => v.len(),
+            Column::Float(v) => v.len(),
+            Column::Boolean(v) => v.len(),
+            Column::Value(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, row: usize) -> Value {
+        match self {
+            Column::Int(v) => Value::Int(v[row]),
+            Column::Float(v) => Value::Float(v[row]),
+            Column::Boolean(v) => Value::Boolean(v[row]),
+            Column::Value(v) => v[row].clone(),
+        }
+    }
+
+    /// Type of every value of the column.
+    fn ty(&self) -> Type {
+        match self {
+            Column::Int(_) => Type::INT,
+            Column::Float(_) => Type::FLOAT,
+            Column::Boolean(_) => Type::BOOLEAN,
+            Column::Value(v) => v
+                .iter()
+                .fold(Type::empty(), |ty, value| ty | Type::of(value)),
+        }
+    }
+
+    fn to_lane(self) -> Lane {
+        match self {
+            Column::Int(v) => Lane::Ints(v.to_vec()),
+            Column::Float(v) => Lane::Floats(v.to_vec()),
+            column => Lane::Values((0..column.len()).map(|row| column.get(row)).collect()),
+        }
+    }
+}
+
+/// A stack entry of the columnar interpreter. Values that are the same for
+/// every row stay scalar, ints and floats are kept unboxed, the latter for the
+/// arithmetic fast path.
+#[derive(Clone)]
+enum Lane {
+    Scalar(Value),
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+    Values(Vec<Value>),
+}
+
+impl Lane {
+    /// Unbox `values` if they are all ints or all floats.
+    fn from_values(values: Vec<Value>) -> Lane {
+        let ints: Option<Vec<_>> = values
+            .iter()
+            .map(|v| match v {
+                Value::Int(v) => Some(*v),
+                _ => None,
+            })
+            .collect();
+        if let Some(ints) = ints {
+            return Lane::Ints(ints);
+        }
+
+        let floats: Option<Vec<_>> = values
+            .iter()
+            .map(|v| match v {
+                Value::Float(v) => Some(*v),
+                _ => None,
+            })
+            .collect();
+        match floats {
+            Some(floats) => Lane::Floats(floats),
+            None => Lane::Values(values),
+        }
+    }
+
+    fn get(&self, row: usize) -> Value {
+        match self {
+            Lane::Scalar(v) => v.clone(),
+            Lane::Ints(v) => Value::Int(v[row]),
+            Lane::Floats(v) => Value::Float(v[row]),
+            Lane::Values(v) => v[row].clone(),
+        }
+    }
+
+    /// The value of every row as a float, if it is one or converts to one
+    /// without changing how it compares.
+    fn floats(&self) -> Option<Floats<'_>> {
+        // 2^53, past it not every integer is a float
+        const EXACT: u64 = 1 << 53;
+        match self {
+            Lane::Floats(v) => Some(Floats::Column(v)),
+            Lane::Ints(v) if v.iter().all(|v| v.unsigned_abs() <= EXACT) => Some(Floats::Ints(v)),
+            Lane::Scalar(Value::Float(v)) => Some(Floats::Scalar(*v)),
+            Lane::Scalar(Value::Int(v)) if v.unsigned_abs() <= EXACT => {
+                Some(Floats::Scalar(*v as f64))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Both operands as floats, if at least one of them is a float and the other
+/// converts to one. Two ints stay ints.
+fn float_operands<'a>(a: &'a Lane, b: &'a Lane) -> Option<(Floats<'a>, Floats<'a>)> {
+    let is_float = |v: &Lane| matches!(v, Lane::Floats(_) | Lane::Scalar(Value::Float(_)));
+    if !is_float(a) && !is_float(b) {
+        return None;
+    }
+
+    Some((a.floats()?, b.floats()?))
+}
+
+enum Floats<'a> {
+    Scalar(f64),
+    Column(&'a [f64]),
+    Ints(&'a [i64]),
+}
+
+impl Floats<'_> {
+    fn get(&self, row: usize) -> f64 {
+        match self {
+            Floats::Scalar(v) => *v,
+            Floats::Column(v) => v[row],
+            Floats::Ints(v) => v[row] as f64,
+        }
+    }
+}
+
+fn map_rows(
+    rows: usize,
+    mut f: impl FnMut(usize) -> Result<Value, RuntimeErrorKind>,
+) -> Result<Lane, RuntimeErrorKind> {
+    (0..rows)
+        .map(&mut f)
+        .collect::<Result<_, _>>()
+        .map(Lane::from_values)
+}
+
+fn binary_op(
+    a: Lane,
+    b: Lane,
+    op: BinaryOp,
+    mode: ArithmeticMode,
+    rows: usize,
+) -> Result<Lane, RuntimeErrorKind> {
+    if let (Lane::Scalar(a), Lane::Scalar(b)) = (&a, &b) {
+        return Value::do_binary_op(a.clone(), b.clone(), op, mode).map(Lane::Scalar);
+    }
+
+    // A float column with another float or an int goes through `f64` just like
+    // `Value::do_binary_op` does, one row at a time
+    if let Some((x, y)) = float_operands(&a, &b) {
+        let floats = |f: fn(f64, f64) -> f64| {
+            Lane::Floats((0..rows).map(|i| f(x.get(i), y.get(i))).collect())
+        };
+        let bools = |f: fn(&f64, &f64) -> bool| {
+            Lane::Values(
+                (0..rows)
+                    .map(|i| Value::Boolean(f(&x.get(i), &y.get(i))))
+                    .collect(),
+            )
+        };
+
+        match op {
+            BinaryOp::Add => return Ok(floats(|a, b| a + b)),
+            BinaryOp::Sub => return Ok(floats(|a, b| a - b)),
+            BinaryOp::Mul => return Ok(floats(|a, b| a * b)),
+            BinaryOp::Div => return Ok(floats(|a, b| a / b)),
+            BinaryOp::Mod => return Ok(floats(|a, b| a % b)),
+            BinaryOp::Pow => return Ok(floats(f64::powf)),
+            BinaryOp::Equal => return Ok(bools(f64::eq)),
+            BinaryOp::NotEqual => return Ok(bools(f64::ne)),
+            BinaryOp::Less => return Ok(bools(f64::lt)),
+            BinaryOp::LessEqual => return Ok(bools(f64::le)),
+            BinaryOp::Greater => return Ok(bools(f64::gt)),
+            BinaryOp::GreaterEqual => return Ok(bools(f64::ge)),
+            _ => {}
+        }
+    }
+
+    map_rows(rows, |row| {
+        Value::do_binary_op(a.get(row), b.get(row), op, mode)
+    })
+}
+
+fn unary_op(
+    v: Lane,
+    op: UnaryOp,
+    mode: ArithmeticMode,
+    rows: usize,
+) -> Result<Lane, RuntimeErrorKind> {
+    match (v, op) {
+        (Lane::Scalar(v), op) => v.do_unary_op(op, mode).map(Lane::Scalar),
+        (Lane::Floats(v), UnaryOp::Neg) => Ok(Lane::Floats(v.into_iter().map(|v| -v).collect())),
+        (v, op) => map_rows(rows, |row| v.get(row).do_unary_op(op, mode)),
+    }
+}
+
+impl Program {
+    /// Evaluate the program once per row, writing the result of row `i` to
+    /// `out[i]`. Each variable of `columns` takes its value from its column,
+    /// which must be as long as `out`, the others are read from `ctx` as in
+    /// `run`. The first failing row aborts the batch, a column of another
+    /// length fails it with `ColumnLength` before any row runs, and so does a
+    /// handle of another registry with `InvalidHandle`.
+    ///
+    /// Every instruction is applied to whole columns at once. Programs with
+    /// branches, from `&&`, `||`, `??` or conditionals, can't skip work for
+    /// part of the rows, they run row by row instead, and so do programs
+    /// that call user functions or lambdas.
+    pub fn run_batch<L: LocalFn>(
+        &self,
+        registry: &Registry<L>,
+        ctx: &Context,
+        columns: &[(VarHandle, Column)],
+        out: &mut [Value],
+    ) -> Result<(), RuntimeError> {
+        let rows = out.len();
+        if !ctx.belongs_to(registry) {
+            return Err(RuntimeErrorKind::Registry(RegistryError::ForeignContext).into());
+        }
+        for (var, _) in columns {
+            registry
+                .check_handle(*var)
+                .map_err(RuntimeErrorKind::Registry)?;
+        }
+        if let Some((_, column)) = columns.iter().find(|(_, column)| column.len() != rows) {
+            return Err(RuntimeErrorKind::ColumnLength {
+                expected: rows,
+                got: column.len(),
+            }
+            .into());
+        }
+
+        if self
+            .instructions
+            .iter()
+            .any(|ix| ix.jump_target().is_some())
+        {
+            let mut ctx = ctx.clone();
+            for (row, out) in out.iter_mut().enumerate() {
+                for (var, column) in columns {
+                    ctx.set_var(*var, column.get(row))
+                        .map_err(RuntimeErrorKind::Registry)?;
+                }
+
+                *out = self.run(registry, &ctx)?;
+            }
+
+            return Ok(());
+        }
+
+        let mut stack = Vec::new();
+        let mut locals = vec![Lane::Scalar(Value::Null); self.locals as usize];
+        for ins in &self.instructions {
+            match ins {
+                Instruction::Noop => {}
+                Instruction::PushLit(v) => stack.push(Lane::Scalar(v.clone())),
+                Instruction::PushVariable { ident } => {
+                    let column = columns.iter().find(|(var, _)| var.slot == *ident);
+                    stack.push(match column {
+                        Some((_, column)) => {
+                            registry.check_var_type(*ident, column.ty())?;
+                            column.to_lane()
+                        }
+                        None => Lane::Scalar(registry.var(*ident, ctx)?),
+                    });
+                }
+                Instruction::Call {
+                    ident,
+                    arg_count,
+                    span,
+                    arg_spans,
+                } => {
+                    let arg_count = *arg_count as usize;
+                    if arg_count > stack.len() {
+                        return Err(RuntimeErrorKind::MalformedInstructionStream.into());
+                    }
+
+                    let args = stack.split_off(stack.len() - arg_count);
+                    let mut row_args = Vec::with_capacity(arg_count);
+                    let ret = map_rows(rows, |row| {
+                        row_args.clear();
+                        row_args.extend(args.iter().map(|arg| arg.get(row)));
+                        registry.call(*ident, &row_args, self.arithmetic)
+                    })
+                    .map_err(|kind| call_error(kind, *span, arg_spans))?;
+                    stack.push(ret);
+                }
+                Instruction::BinaryOp(op, span) => {
+                    let b = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    let a = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = binary_op(a, b, *op, self.arithmetic, rows)
+                        .map_err(|kind| RuntimeError::new(kind, *span))?;
+                    stack.push(ret);
+                }
+                Instruction::UnaryOp(op, span) => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = unary_op(v, *op, self.arithmetic, rows)
+                        .map_err(|kind| RuntimeError::new(kind, *span))?;
+                    stack.push(ret);
+                }
+                Instruction::MakeList { len } => {
+                    let len = *len as usize;
+                    if len > stack.len() {
+                        return Err(RuntimeErrorKind::MalformedInstructionStream.into());
+                    }
+
+                    let items = stack.split_off(stack.len() - len);
+                    let ret = map_rows(rows, |row| {
+                        Ok(Value::List(
+                            items.iter().map(|item| item.get(row)).collect(),
+                        ))
+                    })?;
+                    stack.push(ret);
+                }
+                Instruction::Index(span) => {
+                    let index = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    let list = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = map_rows(rows, |row| list.get(row).index(&index.get(row)))
+                        .map_err(|kind| RuntimeError::new(kind, *span))?;
+                    stack.push(ret);
+                }
+                Instruction::ToBool => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = map_rows(rows, |row| Ok(Value::Boolean(v.get(row).to_bool())))?;
+                    stack.push(ret);
+                }
+                Instruction::PushLocal { slot } => {
+                    let v = locals
+                        .get(*slot as usize)
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    stack.push(v.clone());
+                }
+                Instruction::StoreLocal { slot } => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    let local = locals
+                        .get_mut(*slot as usize)
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    *local = v;
+                }
+                Instruction::CallLocal { .. }
+                | Instruction::Return
+                | Instruction::Jump { .. }
+                | Instruction::JumpIfFalse { .. }
+                | Instruction::JumpIfFalseOrPop { .. }
+                | Instruction::JumpIfTrueOrPop { .. }
+                | Instruction::JumpIfNotNullOrPop { .. }
+                | Instruction::Iterate { .. } => {
+                    unreachable!("Programs with jumps run row by row")
+                }
+            }
+        }
+
+        debug_assert!(stack.len() == 1);
+        let ret = stack
+            .pop()
+            .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+        for (row, out) in out.iter_mut().enumerate() {
+            *out = ret.get(row);
+        }
+
+        Ok(())
+    }
+}
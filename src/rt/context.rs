@@ -0,0 +1,72 @@
+use super::{LocalFn, Registry, RegistryError, Value, VarHandle};
+
+/// Variable values of a single evaluation. A variable the context doesn't set
+/// takes the default value declared in the registry, one it sets must fit the
+/// declared type or running fails with `TypeMismatch`.
+#[derive(Debug, Clone)]
+pub struct Context {
+    registry: u32,
+    values: Vec<Option<Value>>,
+}
+
+impl Context {
+    /// Empty context for programs run against `registry`, only its handles
+    /// are accepted.
+    pub fn new<L: LocalFn>(registry: &Registry<L>) -> Self {
+        Self {
+            registry: registry.id,
+            values: Vec::new(),
+        }
+    }
+
+    /// Set the value of a variable, failing with `InvalidHandle` for a handle
+    /// of another registry.
+    pub fn set_var(
+        &mut self,
+        var: VarHandle,
+        value: impl Into<Value>,
+    ) -> Result<&mut Self, RegistryError> {
+        if var.registry != self.registry {
+            return Err(RegistryError::InvalidHandle(var));
+        }
+
+        let slot = var.slot as usize;
+        if slot >= self.values.len() {
+            self.values.resize(slot + 1, None);
+        }
+
+        self.values[slot] = Some(value.into());
+        Ok(self)
+    }
+
+    pub fn get_var(&self, var: VarHandle) -> Option<&Value> {
+        if var.registry != self.registry {
+            return None;
+        }
+
+        self.value(var.slot)
+    }
+
+    /// Remove the value of a variable, falling back to its default again.
+    pub fn unset_var(&mut self, var: VarHandle) -> Option<Value> {
+        if var.registry != self.registry {
+            return None;
+        }
+
+        self.values.get_mut(var.slot as usize)?.take()
+    }
+
+    /// Remove every value, keeping the allocation for the next evaluation.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Whether the context was made for `registry`.
+    pub(crate) fn belongs_to<L: LocalFn>(&self, registry: &Registry<L>) -> bool {
+        self.registry == registry.id
+    }
+
+    pub(crate) fn value(&self, slot: u32) -> Option<&Value> {
+        self.values.get(slot as usize)?.as_ref()
+    }
+}
@@ -1,12 +1,66 @@
 use std::{error::Error, fmt::Display};
 
-use crate::Span;
+use super::{func::ExternalError, Type, TypeError, VarHandle};
+use crate::{
+    parser::{BinaryOp, UnaryOp},
+    Span,
+};
 
 #[derive(Debug)]
 pub enum RuntimeErrorKind {
     UndeclaredVariable(String),
     UndeclaredFunction(String),
+    /// A function is defined again while an earlier definition is in scope.
+    RedefinedFunction(String),
+    /// A function calls itself, directly or through a function defined in
+    /// its body.
+    RecursiveFunction(String),
+    /// A function is called before the expression defines it.
+    FunctionUsedBeforeDefinition(String),
+    /// The variable is declared without a default value and the context
+    /// doesn't set it.
+    UnsetVariable(String),
     WrongArgumentCount(u32, u32),
+    ArgumentCountOutOfRange {
+        min: u32,
+        max: Option<u32>,
+        got: u32,
+    },
+    IndexOutOfBounds(i64, usize),
+    InvalidIndex(&'static str, &'static str),
+    MissingKey(String),
+    DivisionByZero,
+    IntegerOverflow,
+    /// The shift amount is negative or not less than 64.
+    InvalidShift(i64),
+    InvalidArgument(&'static str, &'static str),
+    /// Arithmetic or bitwise operator applied to a string, list or map, only
+    /// `+` concatenates them.
+    InvalidOperands(BinaryOp, &'static str, &'static str),
+    InvalidOperand(UnaryOp, &'static str),
+    ArgumentTypeMismatch {
+        function: String,
+        index: u32,
+        expected: String,
+        got: &'static str,
+    },
+    ExternalFunction(String, ExternalError),
+    /// A lambda appears somewhere else than as the last argument of a
+    /// higher-order function.
+    MisplacedLambda,
+    /// The last argument of a higher-order function isn't a lambda taking the
+    /// given number of parameters.
+    ExpectedLambda(String, u32),
+    /// Strict compilation rejected the expression, the span points at the
+    /// first error.
+    TypeCheck(Vec<TypeError>),
+    /// The context gives a variable a value that doesn't fit its declaration.
+    Registry(RegistryError),
+    /// A column of a batch doesn't have one value per row.
+    ColumnLength {
+        expected: usize,
+        got: usize,
+    },
     MalformedInstructionStream,
 }
 
@@ -24,6 +78,10 @@ impl RuntimeError {
         }
     }
 
+    pub fn kind(&self) -> &RuntimeErrorKind {
+        &self.kind
+    }
+
     pub fn span(&self) -> Option<Span> {
         self.span
     }
@@ -35,21 +93,183 @@ impl From<RuntimeErrorKind> for RuntimeError {
     }
 }
 
-impl Error for RuntimeError {}
+impl Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.kind.source()
+    }
+}
 
 impl Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.kind {
+        self.kind.fmt(f)
+    }
+}
+
+/// Builtin functions report their failures with a plain kind, which the
+/// runtime unwraps instead of treating it as a foreign error.
+impl Error for RuntimeErrorKind {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RuntimeErrorKind::ExternalFunction(_, err) => Some(err.as_ref()),
+            RuntimeErrorKind::TypeCheck(errors) => Some(&errors[0]),
+            RuntimeErrorKind::Registry(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
             RuntimeErrorKind::UndeclaredVariable(v) => write!(f, "Undeclared variable {}", v),
             RuntimeErrorKind::UndeclaredFunction(v) => write!(f, "Undeclared function {}", v),
+            RuntimeErrorKind::RedefinedFunction(v) => write!(f, "Function {} is already defined", v),
+            RuntimeErrorKind::RecursiveFunction(v) => write!(f, "Function {} cannot call itself", v),
+            RuntimeErrorKind::FunctionUsedBeforeDefinition(v) => {
+                write!(f, "Function {} is called before its definition", v)
+            }
+            RuntimeErrorKind::UnsetVariable(v) => write!(f, "Variable {} has no value", v),
             RuntimeErrorKind::WrongArgumentCount(expected, got) => write!(
                 f,
                 "Function called with wrong number of arguments (expected: {}, got: {})",
                 expected, got
             ),
+            RuntimeErrorKind::IndexOutOfBounds(index, len) => write!(
+                f,
+                "Index {} is out of bounds for list of length {}",
+                index, len
+            ),
+            RuntimeErrorKind::InvalidIndex(target, index) => {
+                write!(f, "Cannot index {} with {}", target, index)
+            }
+            RuntimeErrorKind::MissingKey(key) => write!(f, "Map has no key \"{}\"", key),
+            RuntimeErrorKind::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeErrorKind::IntegerOverflow => write!(f, "Integer overflow"),
+            RuntimeErrorKind::InvalidShift(amount) => {
+                write!(f, "Shift amount {} is out of range (expected: 0 to 63)", amount)
+            }
+            RuntimeErrorKind::InvalidArgument(expected, got) => {
+                write!(f, "Expecting {} argument but got {}", expected, got)
+            }
+            RuntimeErrorKind::InvalidOperands(op, a, b) => write!(
+                f,
+                "Operator {} cannot be applied to {} and {}",
+                op.to_char(),
+                a,
+                b
+            ),
+            RuntimeErrorKind::InvalidOperand(op, a) => {
+                write!(f, "Operator {} cannot be applied to {}", op.to_char(), a)
+            }
+            RuntimeErrorKind::ArgumentCountOutOfRange { min, max, got } => match max {
+                Some(max) => write!(
+                    f,
+                    "Function called with wrong number of arguments (expected: {} to {}, got: {})",
+                    min, max, got
+                ),
+                None => write!(
+                    f,
+                    "Function called with wrong number of arguments (expected: at least {}, got: {})",
+                    min, got
+                ),
+            },
+            RuntimeErrorKind::ArgumentTypeMismatch {
+                function,
+                index,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Argument {} of function {} expects {} but got {}",
+                index + 1,
+                function,
+                expected,
+                got
+            ),
+            RuntimeErrorKind::ExternalFunction(name, err) => {
+                write!(f, "Function {} failed: {}", name, err)
+            }
+            RuntimeErrorKind::MisplacedLambda => write!(
+                f,
+                "Lambdas can only be passed to map, filter, reduce, any and all"
+            ),
+            RuntimeErrorKind::ExpectedLambda(function, params) => write!(
+                f,
+                "Function {} expects a lambda with {} parameter{} as its last argument",
+                function,
+                params,
+                if *params == 1 { "" } else { "s" }
+            ),
+            RuntimeErrorKind::TypeCheck(errors) => {
+                write!(f, "{}", errors[0])?;
+                if errors.len() > 1 {
+                    write!(f, " (and {} more type errors)", errors.len() - 1)?;
+                }
+
+                Ok(())
+            }
+            RuntimeErrorKind::Registry(err) => write!(f, "{}", err),
+            RuntimeErrorKind::ColumnLength { expected, got } => write!(
+                f,
+                "Column has {} values but the batch has {} rows",
+                got, expected
+            ),
             RuntimeErrorKind::MalformedInstructionStream => {
                 write!(f, "Malformed instruction stream")
             }
         }
     }
 }
+
+/// Failure to declare a variable or register a function.
+#[derive(Debug)]
+pub enum RegistryError {
+    DuplicateVariable(String),
+    DuplicateFunction(String),
+    /// The handle doesn't come from this registry.
+    InvalidHandle(VarHandle),
+    /// The value doesn't fit the declared type of the variable.
+    TypeMismatch {
+        name: String,
+        expected: Type,
+        got: Type,
+    },
+    /// The function was registered with `add_fn`, it can't be called from
+    /// several threads.
+    UnsharedFunction(String),
+    /// The context was made for another registry.
+    ForeignContext,
+}
+
+impl Error for RegistryError {}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::DuplicateVariable(v) => write!(f, "Variable {} is already declared", v),
+            RegistryError::DuplicateFunction(v) => {
+                write!(f, "Function {} is already registered", v)
+            }
+            RegistryError::InvalidHandle(handle) => {
+                write!(
+                    f,
+                    "Variable handle {} is unknown to this registry",
+                    handle.slot
+                )
+            }
+            RegistryError::TypeMismatch {
+                name,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Variable {} is declared as {} but got {}",
+                name, expected, got
+            ),
+            RegistryError::ForeignContext => write!(f, "Context was made for another registry"),
+            RegistryError::UnsharedFunction(v) => {
+                write!(f, "Function {} can't be shared between threads", v)
+            }
+        }
+    }
+}
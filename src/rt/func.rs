@@ -1,50 +1,322 @@
-use std::{marker::PhantomData, u32};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    marker::PhantomData,
+    sync::Arc,
+};
 
-use super::Value;
+use super::{RuntimeErrorKind, TryFromValue, Type, Value};
+
+pub type ExternalError = Box<dyn Error + Send + Sync>;
 
 pub struct ExternalFunction<In, F> {
     func: F,
     _marker: PhantomData<In>,
 }
 
+/// Values an external function may return: the Rust types that convert into a
+/// `Value`, or a `Result` whose error aborts the evaluation.
+pub trait IntoFnResult {
+    fn into_fn_result(self) -> Result<Value, ExternalError>;
+
+    /// Kinds of values the function may return, used by the type checker.
+    fn value_type() -> Type;
+}
+
+macro_rules! impl_into_fn_result {
+    ($($rust:ty => $ty:expr),*) => {
+        $(
+            impl IntoFnResult for $rust {
+                fn into_fn_result(self) -> Result<Value, ExternalError> {
+                    Ok(self.into())
+                }
+
+                fn value_type() -> Type {
+                    $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_into_fn_result!(
+    Value => Type::ANY,
+    i64 => Type::INT,
+    f64 => Type::FLOAT,
+    bool => Type::BOOLEAN,
+    String => Type::STRING,
+    &str => Type::STRING,
+    Arc<str> => Type::STRING,
+    Arc<[Value]> => Type::LIST
+);
+
+impl<T: Into<Value>> IntoFnResult for Vec<T> {
+    fn into_fn_result(self) -> Result<Value, ExternalError> {
+        Ok(self.into())
+    }
+
+    fn value_type() -> Type {
+        Type::LIST
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> IntoFnResult for BTreeMap<K, V> {
+    fn into_fn_result(self) -> Result<Value, ExternalError> {
+        Ok(self.into())
+    }
+
+    fn value_type() -> Type {
+        Type::MAP
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> IntoFnResult for HashMap<K, V> {
+    fn into_fn_result(self) -> Result<Value, ExternalError> {
+        Ok(self.into())
+    }
+
+    fn value_type() -> Type {
+        Type::MAP
+    }
+}
+
+impl<T: IntoFnResult> IntoFnResult for Option<T> {
+    fn into_fn_result(self) -> Result<Value, ExternalError> {
+        self.map_or(Ok(Value::Null), T::into_fn_result)
+    }
+
+    fn value_type() -> Type {
+        T::value_type() | Type::NULL
+    }
+}
+
+impl<T: IntoFnResult, E: Error + Send + Sync + 'static> IntoFnResult for Result<T, E> {
+    fn into_fn_result(self) -> Result<Value, ExternalError> {
+        self.map_err(|err| Box::new(err) as ExternalError)?
+            .into_fn_result()
+    }
+
+    fn value_type() -> Type {
+        T::value_type()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    name: Option<Cow<'static, str>>,
+    ty: Type,
+}
+
+impl Param {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+}
+
+/// Description of an external function. Types are captured from the Rust
+/// signature when it's registered, names, purity and documentation can be
+/// filled in with `Registry::add_fn_with`.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    params: Vec<Param>,
+    variadic: Option<Type>,
+    min_args: u32,
+    max_args: Option<u32>,
+    ret: Type,
+    pure: bool,
+    doc: Option<Cow<'static, str>>,
+}
+
+impl Signature {
+    pub(crate) fn new(params: Vec<Type>, variadic: Option<Type>, ret: Type) -> Self {
+        let count = u32::try_from(params.len()).unwrap();
+        Self {
+            params: params
+                .into_iter()
+                .map(|ty| Param { name: None, ty })
+                .collect(),
+            variadic,
+            min_args: count,
+            max_args: variadic.is_none().then_some(count),
+            ret,
+            pure: false,
+            doc: None,
+        }
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// Type of the arguments past the declared parameters, `None` unless the
+    /// function is variadic.
+    pub fn variadic(&self) -> Option<Type> {
+        self.variadic
+    }
+
+    pub fn min_args(&self) -> u32 {
+        self.min_args
+    }
+
+    /// `None` when there's no upper bound.
+    pub fn max_args(&self) -> Option<u32> {
+        self.max_args
+    }
+
+    pub fn ret(&self) -> Type {
+        self.ret
+    }
+
+    pub fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// Type expected of the argument at `index`.
+    pub fn param_type(&self, index: usize) -> Type {
+        self.params
+            .get(index)
+            .map(Param::ty)
+            .or(self.variadic)
+            .unwrap_or(Type::ANY)
+    }
+
+    pub fn accepts(&self, count: u32) -> bool {
+        count >= self.min_args && self.max_args.is_none_or(|max| count <= max)
+    }
+
+    /// Fail with `WrongArgumentCount` or `ArgumentCountOutOfRange` unless the
+    /// function accepts `count` arguments.
+    pub(crate) fn check_arg_count(&self, count: u32) -> Result<(), RuntimeErrorKind> {
+        if self.accepts(count) {
+            return Ok(());
+        }
+
+        Err(match self.max_args {
+            Some(max) if max == self.min_args => RuntimeErrorKind::WrongArgumentCount(max, count),
+            max => RuntimeErrorKind::ArgumentCountOutOfRange {
+                min: self.min_args,
+                max,
+                got: count,
+            },
+        })
+    }
+
+    /// Name the parameters in order, extra names are ignored.
+    pub fn set_param_names<N: Into<Cow<'static, str>>>(
+        &mut self,
+        names: impl IntoIterator<Item = N>,
+    ) -> &mut Self {
+        for (param, name) in self.params.iter_mut().zip(names) {
+            param.name = Some(name.into());
+        }
+
+        self
+    }
+
+    /// Bound the number of arguments of a variadic function. The arity of the
+    /// others is fixed by their parameters and is left untouched.
+    pub fn set_arity(&mut self, min: u32, max: Option<u32>) -> &mut Self {
+        if self.variadic.is_some() {
+            self.min_args = min;
+            self.max_args = max;
+        }
+
+        self
+    }
+
+    /// Mark the function as free of side effects, its result only depends on
+    /// its arguments.
+    pub fn set_pure(&mut self, pure: bool) -> &mut Self {
+        self.pure = pure;
+        self
+    }
+
+    pub fn set_doc(&mut self, doc: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.doc = Some(doc.into());
+        self
+    }
+}
+
 pub trait AnyExternalFunction {
-    fn arg_count(&self) -> u32;
-    fn call(&mut self, args: &[Value]) -> Value;
+    fn signature(&self) -> Signature;
+    fn call(&mut self, args: &[Value]) -> Result<Value, ExternalError>;
+}
+
+/// An external function without mutable state, it can be called from several
+/// threads at once.
+pub trait AnySharedFunction {
+    fn signature(&self) -> Signature;
+    fn call(&self, args: &[Value]) -> Result<Value, ExternalError>;
+}
+
+impl<F: FnMut(&[Value]) -> R, R: IntoFnResult + 'static> AnyExternalFunction
+    for ExternalFunction<fn(&[Value]) -> R, F>
+{
+    fn signature(&self) -> Signature {
+        Signature::new(Vec::new(), Some(Type::ANY), R::value_type())
+    }
+
+    fn call(&mut self, args: &[Value]) -> Result<Value, ExternalError> {
+        (self.func)(args).into_fn_result()
+    }
 }
 
-impl<F: FnMut(&[Value]) -> Value> AnyExternalFunction for ExternalFunction<&[Value], F> {
-    fn arg_count(&self) -> u32 {
-        u32::MAX
+impl<F: Fn(&[Value]) -> R, R: IntoFnResult + 'static> AnySharedFunction
+    for ExternalFunction<fn(&[Value]) -> R, F>
+{
+    fn signature(&self) -> Signature {
+        Signature::new(Vec::new(), Some(Type::ANY), R::value_type())
     }
 
-    fn call(&mut self, args: &[Value]) -> Value {
-        (self.func)(args)
+    fn call(&self, args: &[Value]) -> Result<Value, ExternalError> {
+        (self.func)(args).into_fn_result()
     }
 }
 
+/// Convert the argument at `index`, the registry fills in the function name of
+/// a mismatch.
+fn convert_arg<T: TryFromValue>(args: &[Value], index: usize) -> Result<T, ExternalError> {
+    let arg = &args[index];
+    T::try_from_value(arg.clone()).ok_or_else(|| {
+        Box::new(RuntimeErrorKind::ArgumentTypeMismatch {
+            function: String::new(),
+            index: index as u32,
+            expected: T::type_name().into_owned(),
+            got: arg.type_name(),
+        }) as ExternalError
+    })
+}
+
 macro_rules! impl_ext_func {
     ($($params:ident),*) => {
+        impl_ext_func!(@impl AnyExternalFunction, FnMut, [&mut] $($params),*);
+        impl_ext_func!(@impl AnySharedFunction, Fn, [&] $($params),*);
+    };
+    (@impl $trait:ident, $fn:ident, [$($receiver:tt)*] $($params:ident),*) => {
         #[allow(unused_variables)]
         #[allow(non_snake_case)]
-        impl<F: FnMut($($params),*) -> Value, $($params: From<Value> + 'static),*> AnyExternalFunction for ExternalFunction<($($params ,)*), F> {
-            #[inline(always)]
-            fn arg_count(&self) -> u32 {
-                let count = 0;
-                $(
-                    let $params = count + 1;
-                    let count = $params;
-                )*
-
-                count
+        impl<F: $fn($($params),*) -> R, R: IntoFnResult + 'static, $($params: TryFromValue + 'static),*> $trait for ExternalFunction<fn($($params),*) -> R, F> {
+            fn signature(&self) -> Signature {
+                Signature::new(vec![$($params::value_type()),*], None, R::value_type())
             }
 
-            fn call(&mut self, args: &[Value]) -> Value {
+            fn call($($receiver)* self, args: &[Value]) -> Result<Value, ExternalError> {
+                let index = 0;
                 $(
-                    let $params = args[0].into();
-                    let args = &args[1..];
+                    let $params = convert_arg::<$params>(args, index)?;
+                    let index = index + 1;
                 )*
 
-                (self.func)($($params),*)
+                (self.func)($($params),*).into_fn_result()
             }
         }
     };
@@ -56,8 +328,8 @@ pub trait IntoExtFunc<In> {
     fn into_ext(self) -> Self::Func;
 }
 
-impl<'a, F: FnMut(&[Value]) -> Value> IntoExtFunc<&'a [Value]> for F {
-    type Func = ExternalFunction<&'a [Value], F>;
+impl<F: FnMut(&[Value]) -> R, R: IntoFnResult + 'static> IntoExtFunc<fn(&[Value]) -> R> for F {
+    type Func = ExternalFunction<fn(&[Value]) -> R, F>;
 
     fn into_ext(self) -> Self::Func {
         ExternalFunction {
@@ -69,9 +341,8 @@ impl<'a, F: FnMut(&[Value]) -> Value> IntoExtFunc<&'a [Value]> for F {
 
 macro_rules! impl_into_ext_func {
     ($($params:ident),*) => {
-        #[allow(unused_parens)]
-        impl<F: FnMut($($params),*) -> Value, $($params: From<Value> + 'static),*> IntoExtFunc<($($params),*)> for F {
-            type Func = ExternalFunction<($($params ,)*), F>;
+        impl<F: FnMut($($params),*) -> R, R: IntoFnResult + 'static, $($params: TryFromValue + 'static),*> IntoExtFunc<fn($($params),*) -> R> for F {
+            type Func = ExternalFunction<fn($($params),*) -> R, F>;
 
             fn into_ext(self) -> Self::Func {
                 ExternalFunction {
@@ -1,10 +1,15 @@
-use crate::parser::{BinaryOp, Expr, UnaryOp};
+use crate::{
+    parser::{BinaryOp, Expr, UnaryOp},
+    Span,
+};
 
-use super::{RuntimeError, RuntimeErrorKind, Value};
+use super::{
+    scope::{Resolver, Scope, Unresolved},
+    LocalFn, Registry, RuntimeError, RuntimeErrorKind, Value,
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Instruction {
-    #[allow(dead_code)]
     Noop,
     PushLit(Value),
     PushVariable {
@@ -13,69 +18,458 @@ pub enum Instruction {
     Call {
         ident: u32,
         arg_count: u32,
+        span: Span,
+        /// Spans of the arguments, a type mismatch points at the offending one.
+        arg_spans: Box<[Span]>,
+    },
+    BinaryOp(BinaryOp, Span),
+    UnaryOp(UnaryOp, Span),
+    /// Collect the top `len` values of the stack into a list.
+    MakeList {
+        len: u32,
+    },
+    /// Pop an index then a list and push the indexed element.
+    Index(Span),
+    /// Replace the top of the stack with its truthiness.
+    ToBool,
+    /// Push the value of a local slot.
+    PushLocal {
+        slot: u32,
+    },
+    /// Pop the top of the stack into a local slot.
+    StoreLocal {
+        slot: u32,
+    },
+    /// Pop a list, and the initial accumulator of a `reduce`, then run the
+    /// instructions up to `end` once per element with the lambda's parameters
+    /// in the slots from `slot` on. Execution continues at `end`.
+    Iterate {
+        op: IterOp,
+        slot: u32,
+        end: u32,
+        span: Span,
+    },
+    /// Run the body of an expression-defined function starting at `target`,
+    /// its arguments are on the stack. Execution continues after the call once
+    /// the body reaches `Return`.
+    CallLocal {
+        target: u32,
+    },
+    /// End the body of an expression-defined function, its result is on top of
+    /// the stack.
+    Return,
+    /// Continue at the instruction with index `target`.
+    Jump {
+        target: u32,
+    },
+    /// Pop the top of the stack and jump if it is falsy.
+    JumpIfFalse {
+        target: u32,
+    },
+    /// Jump if the top of the stack is falsy, leaving it in place, otherwise pop it.
+    JumpIfFalseOrPop {
+        target: u32,
+    },
+    /// Jump if the top of the stack is truthy, leaving it in place, otherwise pop it.
+    JumpIfTrueOrPop {
+        target: u32,
+    },
+    /// Jump if the top of the stack is not null, leaving it in place, otherwise pop it.
+    JumpIfNotNullOrPop {
+        target: u32,
     },
-    BinaryOp(BinaryOp),
-    UnaryOp(UnaryOp),
 }
 
-pub(crate) fn write_instruction(
+/// The higher-order functions, they run their lambda inside the program.
+#[derive(Debug, Clone, Copy)]
+pub enum IterOp {
+    Map,
+    Filter,
+    Reduce,
+    Any,
+    All,
+}
+
+impl IterOp {
+    pub(crate) fn from_name(name: &[u8]) -> Option<Self> {
+        Some(match name {
+            b"map" => IterOp::Map,
+            b"filter" => IterOp::Filter,
+            b"reduce" => IterOp::Reduce,
+            b"any" => IterOp::Any,
+            b"all" => IterOp::All,
+            _ => return None,
+        })
+    }
+
+    /// Number of arguments, the lambda included.
+    pub(crate) fn arg_count(self) -> u32 {
+        match self {
+            IterOp::Reduce => 3,
+            _ => 2,
+        }
+    }
+
+    /// Number of parameters of the lambda, `reduce` takes the accumulator
+    /// then the element.
+    pub(crate) fn lambda_params(self) -> u32 {
+        match self {
+            IterOp::Reduce => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Instruction {
+    pub(crate) fn jump_target(&self) -> Option<u32> {
+        match self {
+            Instruction::Iterate { end: target, .. }
+            | Instruction::CallLocal { target }
+            | Instruction::Jump { target }
+            | Instruction::JumpIfFalse { target }
+            | Instruction::JumpIfFalseOrPop { target }
+            | Instruction::JumpIfTrueOrPop { target }
+            | Instruction::JumpIfNotNullOrPop { target } => Some(*target),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn jump_target_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            Instruction::Iterate { end: target, .. }
+            | Instruction::CallLocal { target }
+            | Instruction::Jump { target }
+            | Instruction::JumpIfFalse { target }
+            | Instruction::JumpIfFalseOrPop { target }
+            | Instruction::JumpIfTrueOrPop { target }
+            | Instruction::JumpIfNotNullOrPop { target } => Some(target),
+            _ => None,
+        }
+    }
+}
+
+/// Point the jump at `at` to the end of the instruction stream written so far.
+fn patch_jump(out: &mut [Instruction], at: usize) {
+    let end = u32::try_from(out.len()).unwrap();
+    if let Some(target) = out[at].jump_target_mut() {
+        *target = end;
+    }
+}
+
+/// Compile `expr` against `registry`, returns the instruction stream along
+/// with the number of local slots it uses.
+pub(crate) fn compile<L: LocalFn>(
     expr: &Expr,
-    registry: &super::Registry,
-    out: &mut Vec<Instruction>,
-) -> Result<(), RuntimeError> {
-    match expr {
-        Expr::Literal(v, _) => out.push(Instruction::PushLit(*v)),
-        Expr::Identifier(ident, span) => {
-            let var = registry.var_ident(ident);
-            let ident = var.ok_or_else(|| {
-                RuntimeError::new(
-                    RuntimeErrorKind::UndeclaredVariable(
-                        String::from_utf8_lossy(ident).to_string(),
-                    ),
-                    *span,
-                )
-            })?;
+    registry: &Registry<L>,
+) -> Result<(Vec<Instruction>, u32), RuntimeError> {
+    let mut compiler = Compiler {
+        registry,
+        out: Vec::new(),
+        scope: Scope::new(),
+        next_slot: 0,
+        slots: 0,
+    };
+
+    compiler.write(expr)?;
+    Ok((compiler.out, compiler.slots))
+}
+
+/// A function defined by the expression, its body is compiled once where it
+/// is defined and every call runs it with `CallLocal`.
+#[derive(Clone, Copy)]
+struct UserFn {
+    params: usize,
+    /// Index of the first instruction of the body.
+    target: u32,
+}
 
-            out.push(Instruction::PushVariable { ident });
+struct Compiler<'a, L> {
+    registry: &'a Registry<L>,
+    out: Vec<Instruction>,
+    /// Locals with their slot and functions with their body.
+    scope: Scope<'a, u32, UserFn>,
+    next_slot: u32,
+    /// Number of slots used so far.
+    slots: u32,
+}
+
+impl<'a, L: LocalFn> Resolver<'a> for Compiler<'a, L> {
+    type Local = u32;
+    type Fn = UserFn;
+    type Output = ();
+    type Error = RuntimeError;
+
+    fn scope(&mut self) -> &mut Scope<'a, u32, UserFn> {
+        &mut self.scope
+    }
+
+    fn walk(&mut self, expr: &'a Expr) -> Result<(), RuntimeError> {
+        self.write(expr)
+    }
+
+    fn bind(&mut self, value: &'a Expr) -> Result<u32, RuntimeError> {
+        self.write(value)?;
+        let slot = self.alloc_slot();
+        self.out.push(Instruction::StoreLocal { slot });
+        Ok(slot)
+    }
+
+    fn define(&mut self, params: &'a [Box<[u8]>], body: &'a Expr) -> Result<UserFn, RuntimeError> {
+        let jump = self.out.len();
+        self.out.push(Instruction::Jump { target: 0 });
+
+        // The arguments are popped into fresh slots
+        let target = u32::try_from(self.out.len()).unwrap();
+        let locals = self.scope.locals_len();
+        for param in params {
+            let slot = self.alloc_slot();
+            self.scope.push_local(param, slot);
         }
-        Expr::Call(ident, args, span) => {
-            for arg in args {
-                write_instruction(arg, registry, out)?;
-            }
 
-            let func = registry.fn_ident(ident);
-            let (ident, arg_count) = func.ok_or_else(|| {
-                RuntimeError::new(
-                    RuntimeErrorKind::UndeclaredFunction(
-                        String::from_utf8_lossy(ident).to_string(),
-                    ),
-                    *span,
-                )
-            })?;
+        for (_, slot) in self.scope.locals_since(locals).iter().rev() {
+            self.out.push(Instruction::StoreLocal { slot: *slot });
+        }
+
+        let ret = self.write(body);
+        self.scope.truncate_locals(locals);
+        ret?;
 
-            let supplied_arg_count = u32::try_from(args.len()).unwrap();
-            if arg_count != u32::MAX && supplied_arg_count != arg_count {
+        self.out.push(Instruction::Return);
+        patch_jump(&mut self.out, jump);
+
+        // A function never runs while it is being called, so its slots only
+        // need to stay clear of the code that can call it
+        self.next_slot = self.slots;
+        Ok(UserFn {
+            params: params.len(),
+            target,
+        })
+    }
+
+    fn redefined(&mut self, name: &[u8], span: Span) -> Result<(), RuntimeError> {
+        Err(RuntimeError::new(
+            RuntimeErrorKind::RedefinedFunction(String::from_utf8_lossy(name).to_string()),
+            span,
+        ))
+    }
+}
+
+impl<'a, L: LocalFn> Compiler<'a, L> {
+    fn alloc_slot(&mut self) -> u32 {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.slots = self.slots.max(self.next_slot);
+        slot
+    }
+
+    /// Compile a higher-order function, the lambda's body follows the
+    /// `Iterate` instruction and sees the locals in scope.
+    fn write_iteration(
+        &mut self,
+        op: IterOp,
+        name: &[u8],
+        args: &'a [Expr],
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        let got = u32::try_from(args.len()).unwrap();
+        if got != op.arg_count() {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::WrongArgumentCount(op.arg_count(), got),
+                span,
+            ));
+        }
+
+        let (lambda, args) = args.split_last().unwrap();
+        let (params, body) = match lambda {
+            Expr::Lambda(params, body, _) if params.len() == op.lambda_params() as usize => {
+                (params, body)
+            }
+            _ => {
                 return Err(RuntimeError::new(
-                    RuntimeErrorKind::WrongArgumentCount(arg_count, supplied_arg_count),
-                    *span,
-                ));
+                    RuntimeErrorKind::ExpectedLambda(
+                        String::from_utf8_lossy(name).to_string(),
+                        op.lambda_params(),
+                    ),
+                    lambda.span(),
+                ))
             }
+        };
 
-            out.push(Instruction::Call {
-                ident,
-                arg_count: supplied_arg_count,
-            })
+        for arg in args {
+            self.write(arg)?;
         }
-        Expr::BinaryOp(a, op, b, _) => {
-            write_instruction(a, registry, out)?;
-            write_instruction(b, registry, out)?;
-            out.push(Instruction::BinaryOp(*op));
-        }
-        Expr::UnaryOp(op, expr, _) => {
-            write_instruction(expr, registry, out)?;
-            out.push(Instruction::UnaryOp(*op));
+
+        let next_slot = self.next_slot;
+        let locals = self.scope.locals_len();
+        let slot = self.next_slot;
+        for param in params {
+            let slot = self.alloc_slot();
+            self.scope.push_local(param, slot);
         }
-    };
 
-    Ok(())
+        let at = self.out.len();
+        self.out.push(Instruction::Iterate {
+            op,
+            slot,
+            end: 0,
+            span,
+        });
+
+        let ret = self.write(body);
+        self.scope.truncate_locals(locals);
+        self.next_slot = next_slot;
+        ret?;
+
+        patch_jump(&mut self.out, at);
+        Ok(())
+    }
+
+    fn write(&mut self, expr: &'a Expr) -> Result<(), RuntimeError> {
+        match expr {
+            Expr::Literal(v, _) => self.out.push(Instruction::PushLit(v.clone())),
+            Expr::Identifier(ident, span) => {
+                if let Some(&slot) = self.scope.local(ident) {
+                    self.out.push(Instruction::PushLocal { slot });
+                    return Ok(());
+                }
+
+                let ident = self.registry.var_ident(ident).ok_or_else(|| {
+                    RuntimeError::new(
+                        RuntimeErrorKind::UndeclaredVariable(
+                            String::from_utf8_lossy(ident).to_string(),
+                        ),
+                        *span,
+                    )
+                })?;
+
+                self.out.push(Instruction::PushVariable { ident });
+            }
+            Expr::Call(ident, args, span) => {
+                let user_fn = self.scope.user_fn(ident).copied();
+                let func = self.registry.fn_ident(ident);
+                if let (None, None, Some(op)) = (user_fn, func, IterOp::from_name(ident)) {
+                    return self.write_iteration(op, ident, args, *span);
+                }
+
+                for arg in args {
+                    self.write(arg)?;
+                }
+
+                let supplied_arg_count = u32::try_from(args.len()).unwrap();
+                if let Some(UserFn { params, target }) = user_fn {
+                    let param_count = u32::try_from(params).unwrap();
+                    if param_count != supplied_arg_count {
+                        return Err(RuntimeError::new(
+                            RuntimeErrorKind::WrongArgumentCount(param_count, supplied_arg_count),
+                            *span,
+                        ));
+                    }
+
+                    self.out.push(Instruction::CallLocal { target });
+                    return Ok(());
+                }
+
+                let Some(ident) = func else {
+                    let name = String::from_utf8_lossy(ident).to_string();
+                    let kind = match self.scope.unresolved(ident) {
+                        Unresolved::Recursive => RuntimeErrorKind::RecursiveFunction(name),
+                        Unresolved::UsedBeforeDefinition => {
+                            RuntimeErrorKind::FunctionUsedBeforeDefinition(name)
+                        }
+                        Unresolved::Undeclared => RuntimeErrorKind::UndeclaredFunction(name),
+                    };
+
+                    return Err(RuntimeError::new(kind, *span));
+                };
+
+                let signature = self.registry.signature(ident);
+                signature
+                    .check_arg_count(supplied_arg_count)
+                    .map_err(|kind| RuntimeError::new(kind, *span))?;
+
+                self.out.push(Instruction::Call {
+                    ident,
+                    arg_count: supplied_arg_count,
+                    span: *span,
+                    arg_spans: args.iter().map(Expr::span).collect(),
+                })
+            }
+            Expr::BinaryOp(a, op @ (BinaryOp::LogicalAnd | BinaryOp::LogicalOr), b, _) => {
+                self.write(a)?;
+
+                let jump = self.out.len();
+                self.out.push(match op {
+                    BinaryOp::LogicalAnd => Instruction::JumpIfFalseOrPop { target: 0 },
+                    _ => Instruction::JumpIfTrueOrPop { target: 0 },
+                });
+
+                self.write(b)?;
+                patch_jump(&mut self.out, jump);
+                self.out.push(Instruction::ToBool);
+            }
+            Expr::BinaryOp(a, BinaryOp::NullCoalesce, b, _) => {
+                self.write(a)?;
+
+                let jump = self.out.len();
+                self.out.push(Instruction::JumpIfNotNullOrPop { target: 0 });
+
+                self.write(b)?;
+                patch_jump(&mut self.out, jump);
+            }
+            Expr::BinaryOp(a, op, b, span) => {
+                self.write(a)?;
+                self.write(b)?;
+                self.out.push(Instruction::BinaryOp(*op, *span));
+            }
+            Expr::UnaryOp(op, expr, span) => {
+                self.write(expr)?;
+                self.out.push(Instruction::UnaryOp(*op, *span));
+            }
+            Expr::List(items, _) => {
+                for item in items {
+                    self.write(item)?;
+                }
+
+                self.out.push(Instruction::MakeList {
+                    len: u32::try_from(items.len()).unwrap(),
+                });
+            }
+            Expr::Index(expr, index, span) => {
+                self.write(expr)?;
+                self.write(index)?;
+                self.out.push(Instruction::Index(*span));
+            }
+            Expr::Member(expr, field, span) => {
+                self.write(expr)?;
+                let field = String::from_utf8_lossy(field);
+                self.out
+                    .push(Instruction::PushLit(Value::from(field.as_ref())));
+                self.out.push(Instruction::Index(*span));
+            }
+            Expr::Conditional(cond, a, b, _) => {
+                self.write(cond)?;
+                let jump_else = self.out.len();
+                self.out.push(Instruction::JumpIfFalse { target: 0 });
+
+                self.write(a)?;
+                let jump_end = self.out.len();
+                self.out.push(Instruction::Jump { target: 0 });
+
+                patch_jump(&mut self.out, jump_else);
+                self.write(b)?;
+                patch_jump(&mut self.out, jump_end);
+            }
+            Expr::Let(..) | Expr::Function(..) => {
+                let next_slot = self.next_slot;
+                let ret = self.definitions(expr);
+                self.next_slot = next_slot;
+                return ret;
+            }
+            Expr::Lambda(_, _, span) => {
+                return Err(RuntimeError::new(RuntimeErrorKind::MisplacedLambda, *span));
+            }
+        };
+
+        Ok(())
+    }
 }
@@ -1,80 +1,363 @@
-use crate::parser::{Expr, UnaryOp};
+use std::{ops::Range, slice};
 
+use crate::{parser::Expr, Span};
+
+mod batch;
+mod context;
 mod error;
 mod func;
 mod ix;
 mod opt_pass;
 mod registry;
+mod scope;
+mod typeck;
+mod types;
 mod value;
 
-use self::{error::RuntimeErrorKind, ix::Instruction, opt_pass::run_optimize_pass};
+use self::{
+    ix::{Instruction, IterOp},
+    opt_pass::run_optimize_pass,
+};
 pub use {
-    error::RuntimeError,
-    func::{AnyExternalFunction, IntoExtFunc},
-    registry::Registry,
-    value::Value,
+    batch::Column,
+    context::Context,
+    error::{RegistryError, RuntimeError, RuntimeErrorKind},
+    func::{
+        AnyExternalFunction, AnySharedFunction, ExternalError, IntoExtFunc, IntoFnResult, Param,
+        Signature,
+    },
+    registry::{LocalFn, Registry, SharedRegistry, VarHandle, VarRef, Variable},
+    typeck::{TypeError, TypeErrorKind},
+    types::Type,
+    value::{ArithmeticMode, TryFromValue, Value},
 };
 
 #[derive(Debug)]
 pub struct Program {
     instructions: Vec<ix::Instruction>,
+    /// Number of local slots, from `let` bindings and function parameters.
+    locals: u32,
+    arithmetic: ArithmeticMode,
 }
 
 impl Program {
-    pub fn compile(registry: &Registry, expr: &Expr) -> Result<Program, RuntimeError> {
-        let mut instructions = Vec::new();
-        ix::write_instruction(expr, registry, &mut instructions)?;
+    /// Compile `expr` against `registry`. The program keeps the registry's
+    /// arithmetic mode at this point, constant folding and every later run use it.
+    pub fn compile<L: LocalFn>(
+        registry: &Registry<L>,
+        expr: &Expr,
+    ) -> Result<Program, RuntimeError> {
+        if registry.strict_types {
+            if let Err(errors) = Program::check(registry, expr) {
+                let span = errors[0].span();
+                return Err(RuntimeError::new(RuntimeErrorKind::TypeCheck(errors), span));
+            }
+        }
+
+        let (instructions, locals) = ix::compile(expr, registry)?;
 
-        let instructions = run_optimize_pass(instructions);
-        Ok(Program { instructions })
+        let arithmetic = registry.arithmetic;
+        let instructions = run_optimize_pass(instructions, arithmetic);
+        Ok(Program {
+            instructions,
+            locals,
+            arithmetic,
+        })
     }
 
-    pub fn run(&self, registry: &mut Registry) -> Result<Value, RuntimeError> {
+    /// Infer the type of `expr` without compiling it, reporting every type
+    /// error found along with its span.
+    pub fn check<L: LocalFn>(registry: &Registry<L>, expr: &Expr) -> Result<Type, Vec<TypeError>> {
+        typeck::check(registry, expr)
+    }
+
+    /// Evaluate the program, reading variables from `ctx` first and from the
+    /// defaults of `registry` otherwise. Fails with `ForeignContext` if `ctx`
+    /// was made for another registry.
+    pub fn run<L: LocalFn>(
+        &self,
+        registry: &Registry<L>,
+        ctx: &Context,
+    ) -> Result<Value, RuntimeError> {
+        if !ctx.belongs_to(registry) {
+            return Err(RuntimeErrorKind::Registry(RegistryError::ForeignContext).into());
+        }
+
         let mut stack = Vec::new();
-        for ins in self.instructions.iter().copied() {
+        let mut locals = vec![Value::Null; self.locals as usize];
+        self.exec(
+            registry,
+            ctx,
+            0..self.instructions.len(),
+            &mut stack,
+            &mut locals,
+        )?;
+
+        debug_assert!(stack.len() == 1);
+        stack
+            .pop()
+            .ok_or_else(|| RuntimeErrorKind::MalformedInstructionStream.into())
+    }
+
+    /// Execute the instructions in `range` or until a `Return`. The body of an
+    /// `Iterate` runs through a nested call for every element, and so does the
+    /// body of a function for every `CallLocal`.
+    fn exec<L: LocalFn>(
+        &self,
+        registry: &Registry<L>,
+        ctx: &Context,
+        range: Range<usize>,
+        stack: &mut Vec<Value>,
+        locals: &mut [Value],
+    ) -> Result<(), RuntimeError> {
+        let mut pc = range.start;
+        while pc < range.end {
+            let ins = self
+                .instructions
+                .get(pc)
+                .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+            pc += 1;
             match ins {
                 Instruction::Noop => {}
-                Instruction::PushLit(v) => stack.push(v),
-                Instruction::PushVariable { ident } => stack.push(registry.var(ident)),
-                Instruction::Call { ident, arg_count } => {
-                    let arg_count = arg_count as usize;
+                Instruction::PushLit(v) => stack.push(v.clone()),
+                Instruction::PushVariable { ident } => stack.push(registry.var(*ident, ctx)?),
+                Instruction::Call {
+                    ident,
+                    arg_count,
+                    span,
+                    arg_spans,
+                } => {
+                    let arg_count = *arg_count as usize;
                     if arg_count > stack.len() {
                         return Err(RuntimeErrorKind::MalformedInstructionStream.into());
                     }
 
                     let args = &stack[stack.len() - arg_count..];
-                    let ret = registry.call(ident, args);
+                    let ret = registry
+                        .call(*ident, args, self.arithmetic)
+                        .map_err(|kind| call_error(kind, *span, arg_spans))?;
 
                     stack.drain(stack.len() - arg_count..);
                     stack.push(ret);
                 }
-                Instruction::BinaryOp(op) => {
+                Instruction::BinaryOp(op, span) => {
                     let b = stack
                         .pop()
-                        .ok_or_else(|| RuntimeErrorKind::MalformedInstructionStream)?;
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
                     let a = stack
                         .pop()
-                        .ok_or_else(|| RuntimeErrorKind::MalformedInstructionStream)?;
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = Value::do_binary_op(a, b, *op, self.arithmetic)
+                        .map_err(|kind| RuntimeError::new(kind, *span))?;
+                    stack.push(ret);
+                }
+                Instruction::UnaryOp(op, span) => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = v
+                        .do_unary_op(*op, self.arithmetic)
+                        .map_err(|kind| RuntimeError::new(kind, *span))?;
+                    stack.push(ret);
+                }
+                Instruction::MakeList { len } => {
+                    let len = *len as usize;
+                    if len > stack.len() {
+                        return Err(RuntimeErrorKind::MalformedInstructionStream.into());
+                    }
+
+                    let list = stack.drain(stack.len() - len..).collect();
+                    stack.push(Value::List(list));
+                }
+                Instruction::Index(span) => {
+                    let index = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    let list = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    let ret = list
+                        .index(&index)
+                        .map_err(|kind| RuntimeError::new(kind, *span))?;
+                    stack.push(ret);
+                }
+                Instruction::ToBool => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    stack.push(Value::Boolean(v.to_bool()));
+                }
+                Instruction::PushLocal { slot } => {
+                    let v = locals
+                        .get(*slot as usize)
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    stack.push(v.clone());
+                }
+                Instruction::StoreLocal { slot } => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    let local = locals
+                        .get_mut(*slot as usize)
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    *local = v;
+                }
+                Instruction::CallLocal { target } => {
+                    let body = *target as usize..self.instructions.len();
+                    self.exec(registry, ctx, body, stack, locals)?;
+                }
+                Instruction::Return => return Ok(()),
+                Instruction::Jump { target } => pc = *target as usize,
+                Instruction::JumpIfFalse { target } => {
+                    let v = stack
+                        .pop()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
 
-                    stack.push(Value::do_binary_op(a, b, op));
+                    if !v.to_bool() {
+                        pc = *target as usize;
+                    }
                 }
-                Instruction::UnaryOp(op) => {
+                Instruction::JumpIfFalseOrPop { target } => {
                     let v = stack
+                        .last()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    if v.to_bool() {
+                        stack.pop();
+                    } else {
+                        pc = *target as usize;
+                    }
+                }
+                Instruction::JumpIfTrueOrPop { target } => {
+                    let v = stack
+                        .last()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    if v.to_bool() {
+                        pc = *target as usize;
+                    } else {
+                        stack.pop();
+                    }
+                }
+                Instruction::JumpIfNotNullOrPop { target } => {
+                    let v = stack
+                        .last()
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+
+                    if v.is_null() {
+                        stack.pop();
+                    } else {
+                        pc = *target as usize;
+                    }
+                }
+                Instruction::Iterate {
+                    op,
+                    slot,
+                    end,
+                    span,
+                } => {
+                    let body = pc..*end as usize;
+                    pc = *end as usize;
+
+                    let init = match op {
+                        IterOp::Reduce => stack.pop(),
+                        _ => None,
+                    };
+                    let list = stack
                         .pop()
-                        .ok_or_else(|| RuntimeErrorKind::MalformedInstructionStream)?;
+                        .ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                    let items = match list {
+                        Value::List(items) => items,
+                        Value::Null => {
+                            stack.push(Value::Null);
+                            continue;
+                        }
+                        v => {
+                            return Err(RuntimeError::new(
+                                RuntimeErrorKind::InvalidArgument("list", v.type_name()),
+                                *span,
+                            ))
+                        }
+                    };
+
+                    let slot = *slot as usize;
+                    let mut call = |args: &[Value]| -> Result<Value, RuntimeError> {
+                        locals
+                            .get_mut(slot..slot + args.len())
+                            .ok_or(RuntimeErrorKind::MalformedInstructionStream)?
+                            .clone_from_slice(args);
+
+                        self.exec(registry, ctx, body.clone(), stack, locals)?;
+                        stack
+                            .pop()
+                            .ok_or_else(|| RuntimeErrorKind::MalformedInstructionStream.into())
+                    };
 
                     let ret = match op {
-                        UnaryOp::Neg => v.neg(),
-                        UnaryOp::Not => v.not(),
+                        IterOp::Map => Value::List(
+                            items
+                                .iter()
+                                .map(|item| call(slice::from_ref(item)))
+                                .collect::<Result<_, _>>()?,
+                        ),
+                        IterOp::Filter => {
+                            let mut kept = Vec::new();
+                            for item in items.iter() {
+                                if call(slice::from_ref(item))?.to_bool() {
+                                    kept.push(item.clone());
+                                }
+                            }
+
+                            Value::List(kept.into())
+                        }
+                        IterOp::Any => Value::Boolean(iter_any(&items, |item| {
+                            Ok(call(slice::from_ref(item))?.to_bool())
+                        })?),
+                        IterOp::All => Value::Boolean(!iter_any(&items, |item| {
+                            Ok(!call(slice::from_ref(item))?.to_bool())
+                        })?),
+                        IterOp::Reduce => {
+                            let init = init.ok_or(RuntimeErrorKind::MalformedInstructionStream)?;
+                            items
+                                .iter()
+                                .try_fold(init, |acc, item| call(&[acc, item.clone()]))?
+                        }
                     };
                     stack.push(ret);
                 }
             }
         }
 
-        debug_assert!(stack.len() == 1);
-        stack
-            .pop()
-            .ok_or_else(|| RuntimeErrorKind::MalformedInstructionStream.into())
+        Ok(())
     }
 }
+
+/// Whether `test` holds for some item, stopping at the first one.
+fn iter_any(
+    items: &[Value],
+    mut test: impl FnMut(&Value) -> Result<bool, RuntimeError>,
+) -> Result<bool, RuntimeError> {
+    for item in items {
+        if test(item)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Locate a failed call, a type mismatch points at the offending argument.
+fn call_error(kind: RuntimeErrorKind, span: Span, arg_spans: &[Span]) -> RuntimeError {
+    let span = match kind {
+        RuntimeErrorKind::ArgumentTypeMismatch { index, .. } => {
+            arg_spans.get(index as usize).copied().unwrap_or(span)
+        }
+        _ => span,
+    };
+
+    RuntimeError::new(kind, span)
+}
@@ -1,41 +1,219 @@
 use crate::parser::UnaryOp;
 
-use super::{ix::Instruction, Value};
+use super::{ix::Instruction, ArithmeticMode, Value};
 
-pub(crate) fn run_optimize_pass(mut ix_stream: Vec<Instruction>) -> Vec<Instruction> {
-    for _ in 0..3 {
-        constant_folding(&mut ix_stream);
-        ix_stream.retain(|ix| !matches!(ix, Instruction::Noop));
+pub(crate) fn run_optimize_pass(
+    mut ix_stream: Vec<Instruction>,
+    mode: ArithmeticMode,
+) -> Vec<Instruction> {
+    loop {
+        let folded = constant_folding(&mut ix_stream, mode);
+        let eliminated = dead_code_elimination(&mut ix_stream);
+        if !folded && !eliminated {
+            break;
+        }
+
+        remove_noops(&mut ix_stream);
     }
 
     ix_stream
 }
 
-fn constant_folding(ix_stream: &mut Vec<Instruction>) {
+/// Marks every instruction index that some jump lands on, including the
+/// one-past-the-end index.
+fn jump_targets(ix_stream: &[Instruction]) -> Vec<bool> {
+    let mut targets = vec![false; ix_stream.len() + 1];
+    for ix in ix_stream {
+        if let Some(target) = ix.jump_target() {
+            targets[target as usize] = true;
+        }
+    }
+
+    targets
+}
+
+/// Folds literal operands into their result. Folded instructions are replaced
+/// with `Noop` so jump targets stay valid, and a window is only folded when no
+/// jump lands inside of it. Code following an unconditional jump is left for
+/// `dead_code_elimination` rather than folded.
+fn constant_folding(ix_stream: &mut [Instruction], mode: ArithmeticMode) -> bool {
+    let targets = jump_targets(ix_stream);
+    let mut changed = false;
+    let mut reachable = true;
     for i in 0..ix_stream.len() {
-        if i + 3 <= ix_stream.len() {
-            match &ix_stream[i..i + 3] {
-                [Instruction::PushLit(a), Instruction::PushLit(b), Instruction::BinaryOp(op)] => {
-                    let ret = Value::do_binary_op(*a, *b, *op);
+        reachable |= targets[i];
+        if matches!(ix_stream[i], Instruction::Jump { .. } | Instruction::Return) {
+            reachable = false;
+        }
+
+        if !reachable {
+            continue;
+        }
+
+        if i + 3 <= ix_stream.len() && !targets[i + 1] && !targets[i + 2] {
+            if let [Instruction::PushLit(a), Instruction::PushLit(b), Instruction::BinaryOp(op, _)] =
+                &ix_stream[i..i + 3]
+            {
+                // Leave failing operations for the runtime to report, they may
+                // sit in a branch that never runs
+                if let Ok(ret) = Value::do_binary_op(a.clone(), b.clone(), *op, mode) {
                     ix_stream[i] = Instruction::PushLit(ret);
-                    ix_stream.drain(i + 1..i + 3);
+                    ix_stream[i + 1] = Instruction::Noop;
+                    ix_stream[i + 2] = Instruction::Noop;
+                    changed = true;
                 }
-                _ => {}
             }
-        }
 
-        if i + 2 <= ix_stream.len() {
-            match &ix_stream[i..i + 2] {
-                [Instruction::PushLit(lit), Instruction::UnaryOp(op)] => {
-                    let ret = match op {
-                        UnaryOp::Neg => lit.neg(),
-                        UnaryOp::Not => lit.not(),
-                    };
+            if let [Instruction::PushLit(list), Instruction::PushLit(index), Instruction::Index(_)] =
+                &ix_stream[i..i + 3]
+            {
+                // Leave failing lookups for the runtime to report
+                if let Ok(ret) = list.index(index) {
                     ix_stream[i] = Instruction::PushLit(ret);
-                    ix_stream.remove(i + 1);
+                    ix_stream[i + 1] = Instruction::Noop;
+                    ix_stream[i + 2] = Instruction::Noop;
+                    changed = true;
                 }
-                _ => {}
             }
         }
+
+        if let Instruction::MakeList { len } = ix_stream[i] {
+            let start = i - len as usize;
+            let items = &ix_stream[start..i];
+            if !targets[start + 1..=i].iter().any(|t| *t)
+                && items.iter().all(|ix| matches!(ix, Instruction::PushLit(_)))
+            {
+                let list = items
+                    .iter()
+                    .map(|ix| match ix {
+                        Instruction::PushLit(v) => v.clone(),
+                        _ => unreachable!(),
+                    })
+                    .collect();
+
+                ix_stream[start..i].fill(Instruction::Noop);
+                ix_stream[i] = Instruction::PushLit(Value::List(list));
+                changed = true;
+            }
+        }
+
+        if i + 2 <= ix_stream.len() && !targets[i + 1] {
+            let folded = match &ix_stream[i..i + 2] {
+                [Instruction::PushLit(lit), Instruction::UnaryOp(op, _)] => lit
+                    .do_unary_op(*op, mode)
+                    .ok()
+                    .map(|ret| [Instruction::PushLit(ret), Instruction::Noop]),
+                // `!` already gives a boolean, and negating it twice only
+                // leaves the truthiness
+                [Instruction::UnaryOp(UnaryOp::Not, span), Instruction::ToBool] => {
+                    Some([Instruction::UnaryOp(UnaryOp::Not, *span), Instruction::Noop])
+                }
+                [Instruction::UnaryOp(UnaryOp::Not, _), Instruction::UnaryOp(UnaryOp::Not, _)] => {
+                    Some([Instruction::ToBool, Instruction::Noop])
+                }
+                [Instruction::PushLit(lit), Instruction::ToBool] => Some([
+                    Instruction::PushLit(Value::Boolean(lit.to_bool())),
+                    Instruction::Noop,
+                ]),
+                [Instruction::PushLit(lit), Instruction::JumpIfFalse { target }] => {
+                    Some(if lit.to_bool() {
+                        [Instruction::Noop, Instruction::Noop]
+                    } else {
+                        [Instruction::Noop, Instruction::Jump { target: *target }]
+                    })
+                }
+                [Instruction::PushLit(lit), Instruction::JumpIfFalseOrPop { target }] => {
+                    Some(if lit.to_bool() {
+                        [Instruction::Noop, Instruction::Noop]
+                    } else {
+                        [
+                            Instruction::PushLit(lit.clone()),
+                            Instruction::Jump { target: *target },
+                        ]
+                    })
+                }
+                [Instruction::PushLit(lit), Instruction::JumpIfTrueOrPop { target }] => {
+                    Some(if lit.to_bool() {
+                        [
+                            Instruction::PushLit(lit.clone()),
+                            Instruction::Jump { target: *target },
+                        ]
+                    } else {
+                        [Instruction::Noop, Instruction::Noop]
+                    })
+                }
+                [Instruction::PushLit(lit), Instruction::JumpIfNotNullOrPop { target }] => {
+                    Some(if lit.is_null() {
+                        [Instruction::Noop, Instruction::Noop]
+                    } else {
+                        [
+                            Instruction::PushLit(lit.clone()),
+                            Instruction::Jump { target: *target },
+                        ]
+                    })
+                }
+                _ => None,
+            };
+
+            if let Some([a, b]) = folded {
+                ix_stream[i] = a;
+                ix_stream[i + 1] = b;
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Removes instructions that can never run: everything after an unconditional
+/// jump or a `Return` up to the next jump target, and jumps to the very next
+/// instruction. The body of a function that is never called goes with it.
+fn dead_code_elimination(ix_stream: &mut [Instruction]) -> bool {
+    let targets = jump_targets(ix_stream);
+    let mut changed = false;
+    let mut reachable = true;
+    for i in 0..ix_stream.len() {
+        reachable |= targets[i];
+        if !reachable && !matches!(ix_stream[i], Instruction::Noop) {
+            ix_stream[i] = Instruction::Noop;
+            changed = true;
+            continue;
+        }
+
+        if matches!(ix_stream[i], Instruction::Return) {
+            reachable = false;
+        }
+
+        if let Instruction::Jump { target } = ix_stream[i] {
+            let skipped = ix_stream.get(i + 1..target as usize);
+            if skipped.is_some_and(|ixs| ixs.iter().all(|ix| matches!(ix, Instruction::Noop))) {
+                ix_stream[i] = Instruction::Noop;
+                changed = true;
+            } else {
+                reachable = false;
+            }
+        }
+    }
+
+    changed
+}
+
+fn remove_noops(ix_stream: &mut Vec<Instruction>) {
+    let mut new_index = Vec::with_capacity(ix_stream.len() + 1);
+    let mut count = 0;
+    for ix in ix_stream.iter() {
+        new_index.push(count);
+        if !matches!(ix, Instruction::Noop) {
+            count += 1;
+        }
+    }
+    new_index.push(count);
+
+    ix_stream.retain(|ix| !matches!(ix, Instruction::Noop));
+    for ix in ix_stream.iter_mut() {
+        if let Some(target) = ix.jump_target_mut() {
+            *target = new_index[*target as usize];
+        }
     }
 }
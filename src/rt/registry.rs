@@ -1,41 +1,192 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    convert::Infallible,
+    sync::atomic::{AtomicU32, Ordering},
+};
 
-use super::{AnyExternalFunction, IntoExtFunc, Value};
+use self::interner::Interner;
+use super::{
+    AnyExternalFunction, AnySharedFunction, ArithmeticMode, Context, ExternalError, IntoExtFunc,
+    RegistryError, RuntimeErrorKind, Signature, Type, Value,
+};
 
 mod builtin;
+mod interner;
 
-pub struct Registry {
-    vars: Vec<(Cow<'static, [u8]>, Value)>,
-    fns: Vec<(Cow<'static, [u8]>, Box<dyn AnyExternalFunction>)>,
+type Ident = Cow<'static, [u8]>;
+type Function<L> = (Signature, Callable<L>);
+type LocalCell = RefCell<Box<dyn AnyExternalFunction>>;
+
+enum Callable<L> {
+    /// Functions registered with `add_fn`, held as `L`.
+    Local(L),
+    Shared(Box<dyn AnySharedFunction + Send + Sync>),
+    /// Builtins doing integer arithmetic, they follow the arithmetic mode of
+    /// the calling program.
+    Arithmetic(fn(&[Value], ArithmeticMode) -> Result<Value, RuntimeErrorKind>),
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// How a registry holds the functions registered with `add_fn`. They keep
+/// state between calls and tie the registry to the thread it was built on. A
+/// `SharedRegistry` holds them as `Infallible`, it can't hold any.
+pub trait LocalFn: sealed::Sealed {
+    fn call(&self, args: &[Value]) -> Result<Value, ExternalError>;
+}
+
+impl sealed::Sealed for LocalCell {}
+
+impl LocalFn for LocalCell {
+    fn call(&self, args: &[Value]) -> Result<Value, ExternalError> {
+        self.borrow_mut().call(args)
+    }
+}
+
+impl sealed::Sealed for Infallible {}
+
+impl LocalFn for Infallible {
+    fn call(&self, _: &[Value]) -> Result<Value, ExternalError> {
+        match *self {}
+    }
+}
+
+/// Stable reference to a variable slot, setting a variable through its handle
+/// skips the name lookup. Handles are shared by a registry and the contexts
+/// programs compiled against it run with, and rejected by any other registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VarHandle {
+    pub(crate) registry: u32,
+    pub(crate) slot: u32,
+}
+
+/// Source of the id telling the handles of a registry from the others.
+static NEXT_REGISTRY: AtomicU32 = AtomicU32::new(0);
+
+/// Declaration of a variable in a registry.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    ty: Type,
+    value: Option<Value>,
+}
+
+impl Variable {
+    /// Type the type checker assumes of the variable.
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+
+    /// Value used when the evaluation context doesn't set one.
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+}
+
+/// A variable given either by name or by handle.
+pub enum VarRef {
+    Name(Ident),
+    Handle(VarHandle),
+}
+
+impl From<VarHandle> for VarRef {
+    fn from(handle: VarHandle) -> Self {
+        VarRef::Handle(handle)
+    }
+}
+
+impl<T: Into<Cow<'static, [u8]>>> From<T> for VarRef {
+    fn from(name: T) -> Self {
+        VarRef::Name(name.into())
+    }
+}
+
+/// Functions and the declaration of variables, their values for a single
+/// evaluation are held by a `Context`. Variables and functions live in slots
+/// that compiled programs refer to by index. Removing one leaves an empty slot
+/// behind so the others keep their index.
+///
+/// A registry holding functions registered with `add_fn` stays on the thread
+/// that built it. One without can be frozen with `into_shared` and shared by
+/// every thread running programs compiled against it.
+pub struct Registry<L = LocalCell> {
+    pub(crate) id: u32,
+    names: Interner,
+    vars: Vec<(u32, Option<Variable>)>,
+    var_index: HashMap<u32, u32>,
+    fns: Vec<(u32, Option<Function<L>>)>,
+    fn_index: HashMap<u32, u32>,
+    pub(crate) arithmetic: ArithmeticMode,
+    pub(crate) strict_types: bool,
+}
+
+/// Register the pure function `builtin::$name` under its own name, variadic
+/// functions may give their minimum number of arguments.
+macro_rules! add_builtin {
+    ($registry:ident, $name:ident($($param:literal),*), $doc:literal) => {
+        add_builtin!($registry, $name($($param),*), 0, $doc)
+    };
+    ($registry:ident, $name:ident($($param:literal),*), $min:literal, $doc:literal) => {
+        $registry.add_shared_fn_with(stringify!($name).as_bytes(), builtin::$name, |sig| {
+            sig.set_param_names::<&'static str>([$($param),*])
+                .set_arity($min, None)
+                .set_pure(true)
+                .set_doc($doc);
+        })
+    };
 }
 
 impl Default for Registry {
     fn default() -> Self {
         let mut registry = Self::empty();
-        registry
-            .add_var(b"PI", std::f64::consts::PI)
-            .add_fn(b"pow", builtin::pow)
-            .add_fn(b"sin", builtin::sin)
-            .add_fn(b"cos", builtin::cos)
-            .add_fn(b"tan", builtin::tan)
-            .add_fn(b"asin", builtin::asin)
-            .add_fn(b"acos", builtin::acos)
-            .add_fn(b"atan", builtin::atan)
-            .add_fn(b"sinh", builtin::sinh)
-            .add_fn(b"cosh", builtin::cosh)
-            .add_fn(b"tanh", builtin::tanh)
-            .add_fn(b"asinh", builtin::asinh)
-            .add_fn(b"acosh", builtin::acosh)
-            .add_fn(b"atanh", builtin::atanh)
-            .add_fn(b"exp", builtin::exp)
-            .add_fn(b"ln", builtin::ln)
-            .add_fn(b"log10", builtin::log10)
-            .add_fn(b"log2", builtin::log2)
-            .add_fn(b"sqrt", builtin::sqrt)
-            .add_fn(b"cbrt", builtin::cbrt)
-            .add_fn(b"max", builtin::max)
-            .add_fn(b"min", builtin::min)
-            .add_fn(b"sum", builtin::sum);
+        registry.add_var(b"PI", std::f64::consts::PI);
+        let number = Type::NUMBER | Type::NULL;
+        let mut signature = Signature::new(vec![number, number], None, number);
+        signature
+            .set_param_names(["base", "exp"])
+            .set_pure(true)
+            .set_doc("Raise `base` to the power of `exp`");
+        registry.insert_fn(b"pow".into(), signature, Callable::Arithmetic(builtin::pow));
+        add_builtin!(registry, sin("x"), "Sine of `x` radians");
+        add_builtin!(registry, cos("x"), "Cosine of `x` radians");
+        add_builtin!(registry, tan("x"), "Tangent of `x` radians");
+        add_builtin!(registry, asin("x"), "Arcsine of `x`, in radians");
+        add_builtin!(registry, acos("x"), "Arccosine of `x`, in radians");
+        add_builtin!(registry, atan("x"), "Arctangent of `x`, in radians");
+        add_builtin!(registry, sinh("x"), "Hyperbolic sine of `x`");
+        add_builtin!(registry, cosh("x"), "Hyperbolic cosine of `x`");
+        add_builtin!(registry, tanh("x"), "Hyperbolic tangent of `x`");
+        add_builtin!(registry, asinh("x"), "Inverse hyperbolic sine of `x`");
+        add_builtin!(registry, acosh("x"), "Inverse hyperbolic cosine of `x`");
+        add_builtin!(registry, atanh("x"), "Inverse hyperbolic tangent of `x`");
+        add_builtin!(registry, exp("x"), "`e` raised to the power of `x`");
+        add_builtin!(registry, ln("x"), "Natural logarithm of `x`");
+        add_builtin!(registry, log10("x"), "Base 10 logarithm of `x`");
+        add_builtin!(registry, log2("x"), "Base 2 logarithm of `x`");
+        add_builtin!(registry, sqrt("x"), "Square root of `x`");
+        add_builtin!(registry, cbrt("x"), "Cube root of `x`");
+        add_builtin!(
+            registry,
+            max(),
+            1,
+            "Largest of the numbers given, lists are expanded, null without any"
+        );
+        add_builtin!(
+            registry,
+            min(),
+            1,
+            "Smallest of the numbers given, lists are expanded, null without any"
+        );
+        add_builtin!(
+            registry,
+            sum(),
+            "Sum of the numbers given, lists are expanded, null if they all are"
+        );
+        add_builtin!(registry, len("value"), "Length of a list, string or map");
+        add_builtin!(registry, is_null("value"), "Whether `value` is null");
 
         registry
     }
@@ -44,52 +195,473 @@ impl Default for Registry {
 impl Registry {
     pub fn empty() -> Self {
         Self {
+            id: NEXT_REGISTRY.fetch_add(1, Ordering::Relaxed),
+            names: Interner::default(),
             vars: Vec::new(),
+            var_index: HashMap::new(),
             fns: Vec::new(),
+            fn_index: HashMap::new(),
+            arithmetic: ArithmeticMode::default(),
+            strict_types: false,
         }
     }
 
+    /// Select how integer overflow is handled by programs compiled from now on.
+    pub fn set_arithmetic(&mut self, mode: ArithmeticMode) -> &mut Self {
+        self.arithmetic = mode;
+        self
+    }
+
+    /// When enabled, `Program::compile` type checks the expression first and
+    /// rejects it if any error is found.
+    pub fn set_strict_types(&mut self, enable: bool) -> &mut Self {
+        self.strict_types = enable;
+        self
+    }
+
+    /// Freeze the registry so it can be shared between threads, failing if
+    /// it holds a function registered with `add_fn`.
+    pub fn into_shared(self) -> Result<SharedRegistry, RegistryError> {
+        self.convert_local_fns(|_| None)
+            .map_err(RegistryError::UnsharedFunction)
+    }
+
+    /// Declare a variable with a default value, replacing any variable of
+    /// the same name.
     pub fn add_var(
         &mut self,
         name: impl Into<Cow<'static, [u8]>>,
         value: impl Into<Value>,
     ) -> &mut Self {
-        self.vars.push((name.into(), value.into()));
+        let value = value.into();
+        let handle = self.name_slot(name.into());
+        self.vars[handle.slot as usize].1 = Some(Variable {
+            ty: Type::of(&value),
+            value: Some(value),
+        });
+
         self
     }
 
+    /// Set the default value of a variable and return its handle. A variable
+    /// that isn't declared yet takes the type of the value, a declared one
+    /// keeps its type and fails with `TypeMismatch` if the value doesn't fit.
+    /// A variable keeps its slot for the lifetime of the registry, so programs
+    /// already compiled against it see the new value on their next run, and so
+    /// does a removed variable set again. Fails with `InvalidHandle` for a
+    /// handle this registry never gave out.
+    pub fn set_var(
+        &mut self,
+        var: impl Into<VarRef>,
+        value: impl Into<Value>,
+    ) -> Result<VarHandle, RegistryError> {
+        let value = value.into();
+        let handle = self.slot(var.into())?;
+        let (symbol, var) = &mut self.vars[handle.slot as usize];
+        match var {
+            Some(var) if var.ty.contains(Type::of(&value)) => var.value = Some(value),
+            Some(var) => {
+                return Err(RegistryError::TypeMismatch {
+                    name: String::from_utf8_lossy(self.names.name(*symbol)).to_string(),
+                    expected: var.ty,
+                    got: Type::of(&value),
+                })
+            }
+            None => {
+                *var = Some(Variable {
+                    ty: Type::of(&value),
+                    value: Some(value),
+                })
+            }
+        }
+
+        Ok(handle)
+    }
+
+    /// Declare a variable without a default value, every evaluation context
+    /// then has to set it. The type is what the type checker assumes of it.
+    pub fn declare_var(
+        &mut self,
+        var: impl Into<VarRef>,
+        ty: Type,
+    ) -> Result<VarHandle, RegistryError> {
+        let handle = self.slot(var.into())?;
+        self.vars[handle.slot as usize].1 = Some(Variable { ty, value: None });
+        Ok(handle)
+    }
+
+    /// Declare a variable the host may not know, it evaluates to `null`
+    /// unless the context sets it. Its type gains `NULL`.
+    pub fn declare_optional_var(
+        &mut self,
+        var: impl Into<VarRef>,
+        ty: Type,
+    ) -> Result<VarHandle, RegistryError> {
+        let handle = self.slot(var.into())?;
+        self.vars[handle.slot as usize].1 = Some(Variable {
+            ty: ty | Type::NULL,
+            value: Some(Value::Null),
+        });
+        Ok(handle)
+    }
+
+    /// Slot of a variable, failing for a handle of another registry.
+    fn slot(&mut self, var: VarRef) -> Result<VarHandle, RegistryError> {
+        match var {
+            VarRef::Handle(handle) => self.check_handle(handle).map(|()| handle),
+            VarRef::Name(name) => Ok(self.name_slot(name)),
+        }
+    }
+
+    /// Slot of a variable, allocated on first use of its name.
+    fn name_slot(&mut self, name: Ident) -> VarHandle {
+        let symbol = self.names.intern(name);
+        let slot = *self.var_index.entry(symbol).or_insert_with(|| {
+            self.vars.push((symbol, None));
+            u32::try_from(self.vars.len() - 1).unwrap()
+        });
+
+        VarHandle {
+            registry: self.id,
+            slot,
+        }
+    }
+
+    /// Declare a variable, failing if one of the same name already exists.
+    pub fn try_add_var(
+        &mut self,
+        name: impl Into<Cow<'static, [u8]>>,
+        value: impl Into<Value>,
+    ) -> Result<&mut Self, RegistryError> {
+        let name = name.into();
+        if self.var_ident(&name).is_some() {
+            return Err(RegistryError::DuplicateVariable(
+                String::from_utf8_lossy(&name).to_string(),
+            ));
+        }
+
+        Ok(self.add_var(name, value))
+    }
+
+    /// Remove a variable and return its default value. Programs compiled while
+    /// it was declared fail with `UndeclaredVariable` until it's declared again.
+    pub fn remove_var(&mut self, name: &[u8]) -> Option<Value> {
+        let slot = self.var_ident(name)?;
+        self.vars[slot as usize].1.take()?.value
+    }
+
+    /// Register a function, replacing any function of the same name. The
+    /// registry can't be shared between threads while it holds the function,
+    /// see `add_shared_fn`.
     pub fn add_fn<In: 'static, F: IntoExtFunc<In> + 'static>(
         &mut self,
         name: impl Into<Cow<'static, [u8]>>,
         func: F,
     ) -> &mut Self {
-        self.fns.push((name.into(), Box::new(func.into_ext())));
+        self.add_fn_with(name, func, |_| {})
+    }
+
+    /// Register a function, replacing any function of the same name, and
+    /// complete the signature captured from its types, with parameter names or
+    /// documentation for instance.
+    pub fn add_fn_with<In: 'static, F: IntoExtFunc<In> + 'static>(
+        &mut self,
+        name: impl Into<Cow<'static, [u8]>>,
+        func: F,
+        describe: impl FnOnce(&mut Signature),
+    ) -> &mut Self {
+        let func = func.into_ext();
+        let mut signature = func.signature();
+        describe(&mut signature);
+        self.insert_fn(
+            name.into(),
+            signature,
+            Callable::Local(RefCell::new(Box::new(func))),
+        )
+    }
+
+    /// Register a function that doesn't mutate any state, replacing any
+    /// function of the same name. Unlike the ones registered with `add_fn`, it
+    /// runs concurrently once the registry is shared with `into_shared`.
+    pub fn add_shared_fn<In: 'static, F: IntoExtFunc<In> + 'static>(
+        &mut self,
+        name: impl Into<Cow<'static, [u8]>>,
+        func: F,
+    ) -> &mut Self
+    where
+        F::Func: AnySharedFunction + Send + Sync,
+    {
+        self.add_shared_fn_with(name, func, |_| {})
+    }
+
+    /// `add_shared_fn` completing the signature like `add_fn_with` does.
+    pub fn add_shared_fn_with<In: 'static, F: IntoExtFunc<In> + 'static>(
+        &mut self,
+        name: impl Into<Cow<'static, [u8]>>,
+        func: F,
+        describe: impl FnOnce(&mut Signature),
+    ) -> &mut Self
+    where
+        F::Func: AnySharedFunction + Send + Sync,
+    {
+        let func = func.into_ext();
+        let mut signature = AnySharedFunction::signature(&func);
+        describe(&mut signature);
+        self.insert_fn(name.into(), signature, Callable::Shared(Box::new(func)))
+    }
+
+    fn insert_fn(
+        &mut self,
+        name: Ident,
+        signature: Signature,
+        func: Callable<LocalCell>,
+    ) -> &mut Self {
+        // A replaced or removed function leaves its slot to the new one,
+        // programs compiled against the old one call it once `call` checked
+        // their argument count again
+        let symbol = self.names.intern(name);
+        let slot = *self.fn_index.entry(symbol).or_insert_with(|| {
+            self.fns.push((symbol, None));
+            u32::try_from(self.fns.len() - 1).unwrap()
+        });
+
+        self.fns[slot as usize].1 = Some((signature, func));
         self
     }
 
-    pub(crate) fn var_ident(&self, ident: &[u8]) -> Option<u32> {
+    /// Register a function, failing if one of the same name already exists.
+    pub fn try_add_fn<In: 'static, F: IntoExtFunc<In> + 'static>(
+        &mut self,
+        name: impl Into<Cow<'static, [u8]>>,
+        func: F,
+    ) -> Result<&mut Self, RegistryError> {
+        let name = name.into();
+        if self.fn_ident(&name).is_some() {
+            return Err(RegistryError::DuplicateFunction(
+                String::from_utf8_lossy(&name).to_string(),
+            ));
+        }
+
+        Ok(self.add_fn(name, func))
+    }
+
+    /// Remove a function, returning whether it existed. Programs compiled while
+    /// it was registered fail with `UndeclaredFunction` until it's registered
+    /// again.
+    pub fn remove_fn(&mut self, name: &[u8]) -> bool {
+        let Some(slot) = self.fn_ident(name) else {
+            return false;
+        };
+
+        self.fns[slot as usize].1 = None;
+        true
+    }
+}
+
+impl<L: LocalFn> Registry<L> {
+    /// Handle of a declared variable.
+    pub fn var_handle(&self, name: &[u8]) -> Option<VarHandle> {
+        self.var_ident(name).map(|slot| VarHandle {
+            registry: self.id,
+            slot,
+        })
+    }
+
+    /// Declaration of a variable, `None` if it isn't declared.
+    pub fn get_var(&self, var: impl Into<VarRef>) -> Option<&Variable> {
+        let slot = match var.into() {
+            VarRef::Handle(handle) if handle.registry == self.id => handle.slot,
+            VarRef::Handle(_) => return None,
+            VarRef::Name(name) => self.var_ident(&name)?,
+        };
+
+        self.vars.get(slot as usize)?.1.as_ref()
+    }
+
+    /// Every declared variable, in declaration order.
+    pub fn vars(&self) -> impl Iterator<Item = (&[u8], &Variable)> {
         self.vars
             .iter()
-            .enumerate()
-            .find(|(_, var)| var.0.as_ref() == ident.as_ref())
-            .map(|(i, _)| i as u32)
+            .filter_map(|(symbol, var)| var.as_ref().map(|var| (self.names.name(*symbol), var)))
     }
 
-    pub(crate) fn fn_ident(&self, ident: &[u8]) -> Option<(u32, u32)> {
-        self.fns
-            .iter()
-            .enumerate()
-            .find(|(_, var)| var.0.as_ref() == ident.as_ref())
-            .map(|(i, (_, func))| (i as u32, func.arg_count()))
+    /// Every registered function with its signature, in registration order.
+    pub fn fns(&self) -> impl Iterator<Item = (&[u8], &Signature)> {
+        self.fns.iter().filter_map(|(symbol, func)| {
+            func.as_ref()
+                .map(|(signature, _)| (self.names.name(*symbol), signature))
+        })
+    }
+
+    pub fn fn_signature(&self, name: &[u8]) -> Option<&Signature> {
+        self.fn_ident(name).map(|ident| self.signature(ident))
+    }
+
+    pub(crate) fn check_handle(&self, handle: VarHandle) -> Result<(), RegistryError> {
+        match handle.registry == self.id && (handle.slot as usize) < self.vars.len() {
+            true => Ok(()),
+            false => Err(RegistryError::InvalidHandle(handle)),
+        }
     }
 
-    pub(crate) fn var(&self, ident: u32) -> Value {
-        self.vars[ident as usize].1
+    pub(crate) fn var_ident(&self, ident: &[u8]) -> Option<u32> {
+        let slot = *self.var_index.get(&self.names.get(ident)?)?;
+        self.vars[slot as usize].1.is_some().then_some(slot)
+    }
+
+    pub(crate) fn fn_ident(&self, ident: &[u8]) -> Option<u32> {
+        let slot = *self.fn_index.get(&self.names.get(ident)?)?;
+        self.fns[slot as usize].1.is_some().then_some(slot)
+    }
+
+    /// Signature of a function found with `fn_ident`.
+    pub(crate) fn signature(&self, ident: u32) -> &Signature {
+        let (signature, _) = self.fns[ident as usize]
+            .1
+            .as_ref()
+            .expect("Signature of a removed function");
+        signature
     }
 
-    pub(crate) fn call(&mut self, ident: u32, args: &[Value]) -> Value {
-        let ident = ident as usize;
-        let func = self.fns[ident].1.as_mut();
-        func.call(args)
+    /// Type of a variable found with `var_ident`.
+    pub(crate) fn var_type(&self, ident: u32) -> Type {
+        self.vars[ident as usize]
+            .1
+            .as_ref()
+            .map_or(Type::ANY, |var| var.ty)
+    }
+
+    /// Value of a variable in `ctx`, falling back to its default. A value set
+    /// by the context must fit the declared type.
+    pub(crate) fn var(&self, ident: u32, ctx: &Context) -> Result<Value, RuntimeErrorKind> {
+        let (symbol, var) = &self.vars[ident as usize];
+        let name = || String::from_utf8_lossy(self.names.name(*symbol)).to_string();
+        let var = var
+            .as_ref()
+            .ok_or_else(|| RuntimeErrorKind::UndeclaredVariable(name()))?;
+
+        match ctx.value(ident) {
+            Some(value) => {
+                self.check_var_type(ident, Type::of(value))?;
+                Ok(value.clone())
+            }
+            None => var
+                .value
+                .clone()
+                .ok_or_else(|| RuntimeErrorKind::UnsetVariable(name())),
+        }
+    }
+
+    /// Fail with `TypeMismatch` unless values of type `ty` fit the declaration
+    /// of a variable.
+    pub(crate) fn check_var_type(&self, ident: u32, ty: Type) -> Result<(), RuntimeErrorKind> {
+        let (symbol, var) = &self.vars[ident as usize];
+        match var {
+            Some(var) if !var.ty.contains(ty) => {
+                Err(RuntimeErrorKind::Registry(RegistryError::TypeMismatch {
+                    name: String::from_utf8_lossy(self.names.name(*symbol)).to_string(),
+                    expected: var.ty,
+                    got: ty,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn call(
+        &self,
+        ident: u32,
+        args: &[Value],
+        mode: ArithmeticMode,
+    ) -> Result<Value, RuntimeErrorKind> {
+        let (symbol, func) = &self.fns[ident as usize];
+        let name = self.names.name(*symbol);
+        let Some((signature, func)) = func else {
+            return Err(RuntimeErrorKind::UndeclaredFunction(
+                String::from_utf8_lossy(name).to_string(),
+            ));
+        };
+        signature.check_arg_count(u32::try_from(args.len()).unwrap())?;
+
+        let ret = match func {
+            Callable::Local(func) => func.call(args),
+            Callable::Shared(func) => func.call(args),
+            Callable::Arithmetic(func) => func(args, mode).map_err(ExternalError::from),
+        };
+
+        ret.map_err(|err| match err.downcast::<RuntimeErrorKind>() {
+            Ok(kind) => match *kind {
+                RuntimeErrorKind::ArgumentTypeMismatch {
+                    index,
+                    expected,
+                    got,
+                    ..
+                } => RuntimeErrorKind::ArgumentTypeMismatch {
+                    function: String::from_utf8_lossy(name).to_string(),
+                    index,
+                    expected,
+                    got,
+                },
+                kind => kind,
+            },
+            Err(err) => {
+                RuntimeErrorKind::ExternalFunction(String::from_utf8_lossy(name).to_string(), err)
+            }
+        })
+    }
+}
+
+impl<L> Registry<L> {
+    /// The same registry holding the functions registered with `add_fn` as
+    /// `M`, failing with the name of the first one `convert` refuses.
+    fn convert_local_fns<M>(
+        self,
+        mut convert: impl FnMut(L) -> Option<M>,
+    ) -> Result<Registry<M>, String> {
+        let mut fns = Vec::with_capacity(self.fns.len());
+        for (symbol, func) in self.fns {
+            let func = match func {
+                Some((signature, Callable::Local(func))) => match convert(func) {
+                    Some(func) => Some((signature, Callable::Local(func))),
+                    None => {
+                        return Err(String::from_utf8_lossy(self.names.name(symbol)).to_string())
+                    }
+                },
+                Some((signature, Callable::Shared(func))) => {
+                    Some((signature, Callable::Shared(func)))
+                }
+                Some((signature, Callable::Arithmetic(func))) => {
+                    Some((signature, Callable::Arithmetic(func)))
+                }
+                None => None,
+            };
+            fns.push((symbol, func));
+        }
+
+        Ok(Registry {
+            id: self.id,
+            names: self.names,
+            vars: self.vars,
+            var_index: self.var_index,
+            fns,
+            fn_index: self.fn_index,
+            arithmetic: self.arithmetic,
+            strict_types: self.strict_types,
+        })
+    }
+}
+
+/// A registry that can't change anymore, shared by threads running programs
+/// compiled against it. It holds no function registered with `add_fn`, the
+/// type of those is uninhabited.
+pub type SharedRegistry = Registry<Infallible>;
+
+impl SharedRegistry {
+    /// Thaw the registry to change it.
+    pub fn into_inner(self) -> Registry {
+        match self.convert_local_fns(|never| match never {}) {
+            Ok(registry) => registry,
+            Err(_) => unreachable!("A shared registry holds no local function"),
+        }
     }
 }
@@ -1,25 +1,28 @@
-use crate::Value;
-
-pub fn pow(a: Value, b: Value) -> Value {
-    match (a, b) {
-        (Value::Boolean(_), _) | (_, Value::Boolean(_)) => Value::Boolean(false),
-        (Value::Int(a), Value::Int(b)) => Value::Int(a.pow(b as u32)),
-        _ => {
-            let a = a.to_float();
-            let b = b.to_float();
-            Value::Float(a.powf(b))
-        }
+use crate::{ArithmeticMode, RuntimeErrorKind, Value};
+
+fn expect_number(a: &Value) -> Result<(), RuntimeErrorKind> {
+    match a {
+        Value::Int(_) | Value::Float(_) | Value::Null => Ok(()),
+        _ => Err(RuntimeErrorKind::InvalidArgument("number", a.type_name())),
     }
 }
 
+/// `pow(base, exp)`, integer overflow follows the mode of the calling program
+/// just like `**` does.
+pub fn pow(args: &[Value], mode: ArithmeticMode) -> Result<Value, RuntimeErrorKind> {
+    let [a, b] = args else {
+        return Err(RuntimeErrorKind::MalformedInstructionStream);
+    };
+
+    expect_number(a)?;
+    expect_number(b)?;
+    Value::pow(a, b, mode)
+}
+
 macro_rules! impl_float_fn {
     ($func:ident) => {
-        pub fn $func(a: Value) -> Value {
-            match a {
-                Value::Boolean(_) => Value::Boolean(false),
-                Value::Int(a) => Value::Float((a as f64).$func()),
-                Value::Float(a) => Value::Float(a.$func()),
-            }
+        pub fn $func(a: Option<f64>) -> Option<f64> {
+            a.map(f64::$func)
         }
     };
 }
@@ -43,41 +46,72 @@ impl_float_fn!(log2);
 impl_float_fn!(sqrt);
 impl_float_fn!(cbrt);
 
-pub fn sum(args: &[Value]) -> Value {
-    let mut sum = 0.0;
-    for arg in args {
-        match arg {
-            Value::Boolean(b) => sum += if *b { 1.0 } else { 0.0 },
-            Value::Int(i) => sum += *i as f64,
-            Value::Float(f) => sum += *f,
-        }
-    }
+/// Arguments of the aggregate functions, a list argument contributes each of
+/// its elements so `sum(xs)` and `sum(1, 2, 3)` both work.
+fn expand(args: &[Value]) -> impl Iterator<Item = &Value> {
+    args.iter().flat_map(|arg| match arg {
+        Value::List(items) => items.iter(),
+        arg => std::slice::from_ref(arg).iter(),
+    })
+}
 
-    Value::Float(sum)
+/// `expand` skipping nulls.
+fn flatten(args: &[Value]) -> impl Iterator<Item = &Value> {
+    expand(args).filter(|arg| !arg.is_null())
 }
 
-pub fn max(args: &[Value]) -> Value {
-    let mut max = f64::MIN;
-    for arg in args {
-        match arg {
-            Value::Boolean(b) => max = if *b { 1.0 } else { 0.0 },
-            Value::Int(i) => max = *i as f64,
-            Value::Float(f) => max = *f,
-        }
+/// Whether there are arguments but all of them are null, the aggregate of
+/// unknown values is unknown too.
+fn only_nulls(args: &[Value]) -> bool {
+    let mut items = expand(args);
+    items.next().is_some_and(Value::is_null) && items.all(Value::is_null)
+}
+
+/// `flatten` as floats, failing on anything but numbers.
+fn numbers(args: &[Value]) -> impl Iterator<Item = Result<f64, RuntimeErrorKind>> + '_ {
+    flatten(args).map(|arg| expect_number(arg).map(|()| arg.to_float()))
+}
+
+/// Combine the numbers of `args` with `f`, `None` when there are none.
+fn reduce(args: &[Value], f: fn(f64, f64) -> f64) -> Result<Option<f64>, RuntimeErrorKind> {
+    numbers(args).try_fold(None, |acc, v| {
+        let v = v?;
+        Ok(Some(acc.map_or(v, |acc| f(acc, v))))
+    })
+}
+
+pub fn sum(args: &[Value]) -> Result<Option<f64>, RuntimeErrorKind> {
+    if only_nulls(args) {
+        return Ok(None);
     }
 
-    Value::Float(max)
+    // `Sum for f64` starts from -0.0
+    numbers(args).try_fold(0.0, |sum, v| Ok(sum + v?)).map(Some)
 }
 
-pub fn min(args: &[Value]) -> Value {
-    let mut min = f64::MAX;
-    for arg in args {
-        match arg {
-            Value::Boolean(b) => min = if *b { 1.0 } else { 0.0 },
-            Value::Int(i) => min = *i as f64,
-            Value::Float(f) => min = *f,
+pub fn max(args: &[Value]) -> Result<Option<f64>, RuntimeErrorKind> {
+    reduce(args, f64::max)
+}
+
+pub fn min(args: &[Value]) -> Result<Option<f64>, RuntimeErrorKind> {
+    reduce(args, f64::min)
+}
+
+pub fn len(a: Value) -> Result<Value, RuntimeErrorKind> {
+    Ok(match a {
+        Value::List(v) => Value::Int(v.len() as i64),
+        Value::Str(v) => Value::Int(v.chars().count() as i64),
+        Value::Map(v) => Value::Int(v.len() as i64),
+        Value::Null => Value::Null,
+        a => {
+            return Err(RuntimeErrorKind::InvalidArgument(
+                "list, string or map",
+                a.type_name(),
+            ))
         }
-    }
+    })
+}
 
-    Value::Float(min)
+pub fn is_null(a: Value) -> bool {
+    a.is_null()
 }
@@ -0,0 +1,34 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::Ident;
+
+/// Stores each distinct identifier once and hands out a dense symbol for it,
+/// variables and functions of the same name share their symbol. The map and
+/// the list of names share the same allocation of a name.
+#[derive(Default)]
+pub(super) struct Interner {
+    symbols: HashMap<Arc<[u8]>, u32>,
+    names: Vec<Arc<[u8]>>,
+}
+
+impl Interner {
+    pub(super) fn intern(&mut self, name: Ident) -> u32 {
+        if let Some(symbol) = self.symbols.get(name.as_ref()) {
+            return *symbol;
+        }
+
+        let name = Arc::<[u8]>::from(name.as_ref());
+        let symbol = u32::try_from(self.names.len()).unwrap();
+        self.names.push(Arc::clone(&name));
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub(super) fn get(&self, name: &[u8]) -> Option<u32> {
+        self.symbols.get(name).copied()
+    }
+
+    pub(super) fn name(&self, symbol: u32) -> &[u8] {
+        &self.names[symbol as usize]
+    }
+}
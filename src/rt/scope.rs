@@ -0,0 +1,164 @@
+use crate::{parser::Expr, Span};
+
+/// Names an expression brings in scope, shared by the compiler and the type
+/// checker. A local resolves to `T` and a function the expression defines to
+/// `F`, innermost last.
+pub(crate) struct Scope<'a, T, F> {
+    locals: Vec<(&'a [u8], T)>,
+    fns: Vec<(&'a [u8], F)>,
+    /// Functions whose body is being walked, they can't call themselves.
+    defining: Vec<&'a [u8]>,
+    /// Functions defined further down the chains of definitions being
+    /// walked, the next one last.
+    pending: Vec<&'a [u8]>,
+}
+
+/// Why a call names no function in scope nor in the registry.
+pub(crate) enum Unresolved {
+    /// The function calls itself, directly or through a function defined in
+    /// its body.
+    Recursive,
+    /// The function is defined further down the expression.
+    UsedBeforeDefinition,
+    Undeclared,
+}
+
+impl<'a, T, F> Scope<'a, T, F> {
+    pub(crate) fn new() -> Self {
+        Self {
+            locals: Vec::new(),
+            fns: Vec::new(),
+            defining: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub(crate) fn local(&self, name: &[u8]) -> Option<&T> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|(local, _)| *local == name)
+            .map(|(_, local)| local)
+    }
+
+    pub(crate) fn user_fn(&self, name: &[u8]) -> Option<&F> {
+        self.fns
+            .iter()
+            .rev()
+            .find(|(func, _)| *func == name)
+            .map(|(_, func)| func)
+    }
+
+    pub(crate) fn unresolved(&self, name: &[u8]) -> Unresolved {
+        if self.defining.contains(&name) {
+            Unresolved::Recursive
+        } else if self.pending.contains(&name) {
+            Unresolved::UsedBeforeDefinition
+        } else {
+            Unresolved::Undeclared
+        }
+    }
+
+    /// Bring a local in scope until the locals are truncated back.
+    pub(crate) fn push_local(&mut self, name: &'a [u8], local: T) {
+        self.locals.push((name, local));
+    }
+
+    /// Number of locals in scope, for `truncate_locals`.
+    pub(crate) fn locals_len(&self) -> usize {
+        self.locals.len()
+    }
+
+    /// The locals brought in scope since there were `len` of them.
+    pub(crate) fn locals_since(&self, len: usize) -> &[(&'a [u8], T)] {
+        &self.locals[len..]
+    }
+
+    pub(crate) fn truncate_locals(&mut self, len: usize) {
+        self.locals.truncate(len);
+    }
+}
+
+/// A walk over an expression that brings its `let` bindings and function
+/// definitions in scope. The compiler and the type checker implement the
+/// hooks, `definitions` resolves the names the same way for both.
+pub(crate) trait Resolver<'a> {
+    type Local;
+    type Fn;
+    type Output;
+    type Error;
+
+    fn scope(&mut self) -> &mut Scope<'a, Self::Local, Self::Fn>;
+
+    fn walk(&mut self, expr: &'a Expr) -> Result<Self::Output, Self::Error>;
+
+    /// Walk the value of a `let` binding, returning what its name resolves
+    /// to.
+    fn bind(&mut self, value: &'a Expr) -> Result<Self::Local, Self::Error>;
+
+    /// Walk the body of a function with its parameters, returning what calls
+    /// to it resolve to. The body sees the locals and functions in scope, but
+    /// not the function itself.
+    fn define(&mut self, params: &'a [Box<[u8]>], body: &'a Expr) -> Result<Self::Fn, Self::Error>;
+
+    /// A function is defined again while an earlier definition is in scope.
+    fn redefined(&mut self, name: &[u8], span: Span) -> Result<(), Self::Error>;
+
+    /// Walk a chain of `let` bindings and function definitions, then the
+    /// expression they are in scope of.
+    fn definitions(&mut self, mut expr: &'a Expr) -> Result<Self::Output, Self::Error> {
+        let mut chain = Vec::new();
+        while let Expr::Let(_, _, next, _) | Expr::Function(_, _, _, next, _) = expr {
+            chain.push(expr);
+            expr = next;
+        }
+
+        let scope = self.scope();
+        let pending = scope.pending.len();
+        scope
+            .pending
+            .extend(chain.iter().rev().filter_map(|def| match def {
+                Expr::Function(name, ..) => Some(name.as_ref()),
+                _ => None,
+            }));
+
+        let locals = scope.locals.len();
+        let fns = scope.fns.len();
+        let ret = chain
+            .into_iter()
+            .try_for_each(|def| self.definition(def))
+            .and_then(|()| self.walk(expr));
+
+        let scope = self.scope();
+        scope.pending.truncate(pending);
+        scope.locals.truncate(locals);
+        scope.fns.truncate(fns);
+        ret
+    }
+
+    /// Walk one `let` binding or function definition and bring it in scope.
+    fn definition(&mut self, def: &'a Expr) -> Result<(), Self::Error> {
+        match def {
+            Expr::Let(name, value, _, _) => {
+                let local = self.bind(value)?;
+                self.scope().locals.push((name, local));
+            }
+            Expr::Function(name, params, body, _, span) => {
+                let scope = self.scope();
+                scope.pending.pop();
+                if scope.fns.iter().any(|(func, _)| *func == name.as_ref()) {
+                    self.redefined(name, *span)?;
+                }
+
+                self.scope().defining.push(name);
+                let func = self.define(params, body);
+                self.scope().defining.pop();
+                let func = func?;
+                self.scope().fns.push((name, func));
+            }
+            _ => unreachable!("Only definitions are chained"),
+        }
+
+        Ok(())
+    }
+}
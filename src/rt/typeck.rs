@@ -0,0 +1,499 @@
+use std::{convert::Infallible, error::Error, fmt::Display};
+
+use crate::{
+    parser::{BinaryOp, Expr, UnaryOp},
+    Span,
+};
+
+use super::{
+    ix::IterOp,
+    scope::{Resolver, Scope, Unresolved},
+    ArithmeticMode, LocalFn, Registry, Type,
+};
+
+#[derive(Debug)]
+pub enum TypeErrorKind {
+    UndeclaredVariable(String),
+    UndeclaredFunction(String),
+    RedefinedFunction(String),
+    RecursiveFunction(String),
+    FunctionUsedBeforeDefinition(String),
+    WrongArgumentCount(u32, u32),
+    ArgumentCountOutOfRange {
+        min: u32,
+        max: Option<u32>,
+        got: u32,
+    },
+    ArgumentTypeMismatch {
+        function: String,
+        index: u32,
+        expected: Type,
+        got: Type,
+    },
+    InvalidOperands(BinaryOp, Type, Type),
+    InvalidOperand(UnaryOp, Type),
+    InvalidIndex(Type, Type),
+    MisplacedLambda,
+    ExpectedLambda(String, u32),
+}
+
+#[derive(Debug)]
+pub struct TypeError {
+    kind: TypeErrorKind,
+    span: Span,
+}
+
+impl TypeError {
+    pub fn kind(&self) -> &TypeErrorKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Error for TypeError {}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            TypeErrorKind::UndeclaredVariable(v) => write!(f, "Undeclared variable {}", v),
+            TypeErrorKind::UndeclaredFunction(v) => write!(f, "Undeclared function {}", v),
+            TypeErrorKind::RedefinedFunction(v) => write!(f, "Function {} is already defined", v),
+            TypeErrorKind::RecursiveFunction(v) => write!(f, "Function {} cannot call itself", v),
+            TypeErrorKind::FunctionUsedBeforeDefinition(v) => {
+                write!(f, "Function {} is called before its definition", v)
+            }
+            TypeErrorKind::WrongArgumentCount(expected, got) => write!(
+                f,
+                "Function called with wrong number of arguments (expected: {}, got: {})",
+                expected, got
+            ),
+            TypeErrorKind::ArgumentCountOutOfRange { min, max, got } => match max {
+                Some(max) => write!(
+                    f,
+                    "Function called with wrong number of arguments (expected: {} to {}, got: {})",
+                    min, max, got
+                ),
+                None => write!(
+                    f,
+                    "Function called with wrong number of arguments (expected: at least {}, got: {})",
+                    min, got
+                ),
+            },
+            TypeErrorKind::ArgumentTypeMismatch {
+                function,
+                index,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Argument {} of function {} expects {} but got {}",
+                index + 1,
+                function,
+                expected,
+                got
+            ),
+            TypeErrorKind::InvalidOperands(op, a, b) => write!(
+                f,
+                "Operator {} cannot be applied to {} and {}",
+                op.to_char(),
+                a,
+                b
+            ),
+            TypeErrorKind::InvalidOperand(op, a) => {
+                write!(f, "Operator {} cannot be applied to {}", op.to_char(), a)
+            }
+            TypeErrorKind::InvalidIndex(target, index) => {
+                write!(f, "Cannot index {} with {}", target, index)
+            }
+            TypeErrorKind::MisplacedLambda => write!(
+                f,
+                "Lambdas can only be passed to map, filter, reduce, any and all"
+            ),
+            TypeErrorKind::ExpectedLambda(function, params) => write!(
+                f,
+                "Function {} expects a lambda with {} parameter{} as its last argument",
+                function,
+                params,
+                if *params == 1 { "" } else { "s" }
+            ),
+        }
+    }
+}
+
+/// Infer the type of `expr` against the variables and function signatures of
+/// `registry`, collecting every error instead of stopping at the first one.
+pub(crate) fn check<'a, L: LocalFn>(
+    registry: &'a Registry<L>,
+    expr: &'a Expr,
+) -> Result<Type, Vec<TypeError>> {
+    let mut checker = Checker {
+        registry,
+        scope: Scope::new(),
+        errors: Vec::new(),
+    };
+
+    let ty = checker.infer(expr);
+    if checker.errors.is_empty() {
+        Ok(ty)
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct Checker<'a, L> {
+    registry: &'a Registry<L>,
+    /// Locals with their type and functions with their parameter count and
+    /// return type.
+    scope: Scope<'a, Type, (u32, Type)>,
+    errors: Vec<TypeError>,
+}
+
+impl<'a, L: LocalFn> Resolver<'a> for Checker<'a, L> {
+    type Local = Type;
+    type Fn = (u32, Type);
+    type Output = Type;
+    type Error = Infallible;
+
+    fn scope(&mut self) -> &mut Scope<'a, Type, (u32, Type)> {
+        &mut self.scope
+    }
+
+    fn walk(&mut self, expr: &'a Expr) -> Result<Type, Infallible> {
+        Ok(self.infer(expr))
+    }
+
+    fn bind(&mut self, value: &'a Expr) -> Result<Type, Infallible> {
+        Ok(self.infer(value))
+    }
+
+    fn define(
+        &mut self,
+        params: &'a [Box<[u8]>],
+        body: &'a Expr,
+    ) -> Result<(u32, Type), Infallible> {
+        // Parameters are untyped, the body is checked once with each of them
+        // as `any`
+        let len = self.scope.locals_len();
+        for param in params {
+            self.scope.push_local(param, Type::ANY);
+        }
+
+        let ret = self.infer(body);
+        self.scope.truncate_locals(len);
+        Ok((u32::try_from(params.len()).unwrap(), ret))
+    }
+
+    fn redefined(&mut self, name: &[u8], span: Span) -> Result<(), Infallible> {
+        self.error(
+            TypeErrorKind::RedefinedFunction(String::from_utf8_lossy(name).to_string()),
+            span,
+        );
+        Ok(())
+    }
+}
+
+impl<'a, L: LocalFn> Checker<'a, L> {
+    /// Report an error, the failing expression is then treated as `ANY` so a
+    /// single mistake doesn't cascade through its parents.
+    fn error(&mut self, kind: TypeErrorKind, span: Span) -> Type {
+        self.errors.push(TypeError { kind, span });
+        Type::ANY
+    }
+
+    fn infer(&mut self, expr: &'a Expr) -> Type {
+        match expr {
+            Expr::Literal(v, _) => Type::of(v),
+            Expr::Identifier(ident, span) => {
+                if let Some(&ty) = self.scope.local(ident) {
+                    return ty;
+                }
+
+                match self.registry.var_ident(ident) {
+                    Some(ident) => self.registry.var_type(ident),
+                    None => self.error(
+                        TypeErrorKind::UndeclaredVariable(
+                            String::from_utf8_lossy(ident).to_string(),
+                        ),
+                        *span,
+                    ),
+                }
+            }
+            Expr::Call(ident, args, span) => {
+                let user_fn = self.scope.user_fn(ident).copied();
+                let func = self.registry.fn_ident(ident);
+                if let (None, None, Some(op)) = (user_fn, func, IterOp::from_name(ident)) {
+                    return self.iteration(op, ident, args, *span);
+                }
+
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|arg| (self.infer(arg), arg.span()))
+                    .collect();
+                let got = u32::try_from(args.len()).unwrap();
+                if let Some((params, ret)) = user_fn {
+                    if params != got {
+                        return self.error(TypeErrorKind::WrongArgumentCount(params, got), *span);
+                    }
+
+                    return ret;
+                }
+
+                let name = String::from_utf8_lossy(ident).to_string();
+                let Some(ident) = func else {
+                    let kind = match self.scope.unresolved(ident) {
+                        Unresolved::Recursive => TypeErrorKind::RecursiveFunction(name),
+                        Unresolved::UsedBeforeDefinition => {
+                            TypeErrorKind::FunctionUsedBeforeDefinition(name)
+                        }
+                        Unresolved::Undeclared => TypeErrorKind::UndeclaredFunction(name),
+                    };
+
+                    return self.error(kind, *span);
+                };
+
+                let signature = self.registry.signature(ident);
+                if !signature.accepts(got) {
+                    let kind = match signature.max_args() {
+                        Some(max) if max == signature.min_args() => {
+                            TypeErrorKind::WrongArgumentCount(max, got)
+                        }
+                        max => TypeErrorKind::ArgumentCountOutOfRange {
+                            min: signature.min_args(),
+                            max,
+                            got,
+                        },
+                    };
+
+                    return self.error(kind, *span);
+                }
+
+                for (index, (arg, span)) in args.into_iter().enumerate() {
+                    let param = signature.param_type(index);
+                    if !param.intersects(arg) {
+                        self.error(
+                            TypeErrorKind::ArgumentTypeMismatch {
+                                function: name.clone(),
+                                index: u32::try_from(index).unwrap(),
+                                expected: param,
+                                got: arg,
+                            },
+                            span,
+                        );
+                    }
+                }
+
+                signature.ret()
+            }
+            Expr::BinaryOp(a, op, b, span) => {
+                let a = self.infer(a);
+                let b = self.infer(b);
+                let mut ret = Type::empty();
+                for a in a.kinds() {
+                    for b in b.kinds() {
+                        if let Some(ty) = binary_op(*op, a, b, self.registry.arithmetic) {
+                            ret |= ty;
+                        }
+                    }
+                }
+
+                if ret.is_empty() {
+                    return self.error(TypeErrorKind::InvalidOperands(*op, a, b), *span);
+                }
+
+                ret
+            }
+            Expr::UnaryOp(op, expr, span) => {
+                let a = self.infer(expr);
+                let mut ret = Type::empty();
+                for a in a.kinds() {
+                    if let Some(ty) = unary_op(*op, a, self.registry.arithmetic) {
+                        ret |= ty;
+                    }
+                }
+
+                if ret.is_empty() {
+                    return self.error(TypeErrorKind::InvalidOperand(*op, a), *span);
+                }
+
+                ret
+            }
+            Expr::Conditional(cond, a, b, _) => {
+                self.infer(cond);
+                self.infer(a) | self.infer(b)
+            }
+            Expr::List(items, _) => {
+                for item in items {
+                    self.infer(item);
+                }
+
+                Type::LIST
+            }
+            Expr::Index(expr, index, span) => {
+                let target = self.infer(expr);
+                let index = self.infer(index);
+                self.index(target, index, *span)
+            }
+            Expr::Member(expr, _, span) => {
+                let target = self.infer(expr);
+                self.index(target, Type::STRING, *span)
+            }
+            Expr::Let(..) | Expr::Function(..) => {
+                let Ok(ty) = self.definitions(expr);
+                ty
+            }
+            Expr::Lambda(_, _, span) => self.error(TypeErrorKind::MisplacedLambda, *span),
+        }
+    }
+
+    /// Infer a higher-order function, the lambda's parameters are `any` as
+    /// lists don't carry the type of their elements.
+    fn iteration(&mut self, op: IterOp, name: &[u8], args: &'a [Expr], span: Span) -> Type {
+        let name = String::from_utf8_lossy(name).to_string();
+        let got = u32::try_from(args.len()).unwrap();
+        if got != op.arg_count() {
+            for arg in args.iter().filter(|arg| !matches!(arg, Expr::Lambda(..))) {
+                self.infer(arg);
+            }
+
+            return self.error(TypeErrorKind::WrongArgumentCount(op.arg_count(), got), span);
+        }
+
+        let (lambda, args) = args.split_last().unwrap();
+        let list = self.infer(&args[0]);
+        if !list.intersects(Type::LIST | Type::NULL) {
+            self.error(
+                TypeErrorKind::ArgumentTypeMismatch {
+                    function: name.clone(),
+                    index: 0,
+                    expected: Type::LIST,
+                    got: list,
+                },
+                args[0].span(),
+            );
+        }
+
+        let init = args.get(1).map(|init| self.infer(init));
+        let (params, body) = match lambda {
+            Expr::Lambda(params, body, _) if params.len() == op.lambda_params() as usize => {
+                (params, body)
+            }
+            _ => {
+                return self.error(
+                    TypeErrorKind::ExpectedLambda(name, op.lambda_params()),
+                    lambda.span(),
+                )
+            }
+        };
+
+        let len = self.scope.locals_len();
+        for param in params {
+            self.scope.push_local(param, Type::ANY);
+        }
+
+        let body = self.infer(body);
+        self.scope.truncate_locals(len);
+
+        let ret = match op {
+            IterOp::Map | IterOp::Filter => Type::LIST,
+            IterOp::Any | IterOp::All => Type::BOOLEAN,
+            IterOp::Reduce => init.unwrap_or(Type::ANY) | body,
+        };
+
+        if list.contains(Type::NULL) {
+            ret | Type::NULL
+        } else {
+            ret
+        }
+    }
+
+    fn index(&mut self, target: Type, index: Type, span: Span) -> Type {
+        let list = target.intersects(Type::LIST) && index.intersects(Type::INT);
+        let map = target.intersects(Type::MAP) && index.intersects(Type::STRING);
+        if list || map {
+            Type::ANY
+        } else if target.contains(Type::NULL) {
+            Type::NULL
+        } else {
+            self.error(TypeErrorKind::InvalidIndex(target, index), span)
+        }
+    }
+}
+
+/// Result of an integer operation, which may overflow into a float.
+fn int_result(mode: ArithmeticMode) -> Type {
+    match mode {
+        ArithmeticMode::PromoteToFloat => Type::NUMBER,
+        _ => Type::INT,
+    }
+}
+
+/// Result of `op` applied to two single kinds, `None` when it is rejected.
+fn binary_op(op: BinaryOp, a: Type, b: Type, mode: ArithmeticMode) -> Option<Type> {
+    let both = a | b;
+    match op {
+        BinaryOp::NullCoalesce => Some(if a == Type::NULL { b } else { a }),
+        BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::LogicalAnd | BinaryOp::LogicalOr => {
+            Some(Type::BOOLEAN)
+        }
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+            let comparable = (Type::NUMBER | Type::BOOLEAN).contains(both)
+                || a == b
+                || both.contains(Type::NULL);
+            comparable.then_some(Type::BOOLEAN)
+        }
+        BinaryOp::Add if both.contains(Type::NULL) => (Type::NUMBER | Type::STRING | Type::LIST)
+            .contains(both.without(Type::NULL))
+            .then_some(Type::NULL),
+        BinaryOp::Add if both.contains(Type::STRING) => Some(Type::STRING),
+        BinaryOp::Add if both == Type::LIST => Some(Type::LIST),
+        BinaryOp::Add
+        | BinaryOp::Sub
+        | BinaryOp::Mul
+        | BinaryOp::Div
+        | BinaryOp::Mod
+        | BinaryOp::Pow => {
+            if both.contains(Type::NULL) {
+                Type::NUMBER
+                    .contains(both.without(Type::NULL))
+                    .then_some(Type::NULL)
+            } else if both == Type::INT && matches!(op, BinaryOp::Pow) {
+                // A negative exponent gives a float
+                Some(int_result(mode) | Type::FLOAT)
+            } else if both == Type::INT {
+                Some(int_result(mode))
+            } else {
+                Type::NUMBER.contains(both).then_some(Type::FLOAT)
+            }
+        }
+        BinaryOp::BitAnd
+        | BinaryOp::BitOr
+        | BinaryOp::BitXor
+        | BinaryOp::ShiftLeft
+        | BinaryOp::ShiftRight
+        | BinaryOp::UnsignedShiftRight => {
+            let ret = if both.contains(Type::NULL) {
+                Type::NULL
+            } else {
+                Type::INT
+            };
+            (Type::INT | Type::BOOLEAN | Type::NULL)
+                .contains(both)
+                .then_some(ret)
+        }
+    }
+}
+
+/// Result of `op` applied to a single kind, `None` when it is rejected.
+fn unary_op(op: UnaryOp, a: Type, mode: ArithmeticMode) -> Option<Type> {
+    match (op, a) {
+        (UnaryOp::Not, _) => Some(Type::BOOLEAN),
+        (_, Type::NULL) => Some(Type::NULL),
+        (UnaryOp::Neg, Type::INT) => Some(int_result(mode)),
+        (UnaryOp::Neg, Type::FLOAT) => Some(Type::FLOAT),
+        (UnaryOp::BitNot, Type::INT | Type::BOOLEAN) => Some(Type::INT),
+        _ => None,
+    }
+}
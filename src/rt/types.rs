@@ -0,0 +1,102 @@
+use std::{
+    fmt::{Debug, Display},
+    ops::{BitOr, BitOrAssign},
+};
+
+use super::Value;
+
+/// The set of kinds a value may have at runtime. Checking is optimistic, an
+/// operation is only rejected when none of the kinds it could see are valid.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Type(u8);
+
+impl Type {
+    pub const INT: Type = Type(1 << 0);
+    pub const FLOAT: Type = Type(1 << 1);
+    pub const BOOLEAN: Type = Type(1 << 2);
+    pub const STRING: Type = Type(1 << 3);
+    pub const LIST: Type = Type(1 << 4);
+    pub const MAP: Type = Type(1 << 5);
+    pub const NULL: Type = Type(1 << 6);
+    pub const NUMBER: Type = Type(Self::INT.0 | Self::FLOAT.0);
+    pub const ANY: Type = Type((1 << 7) - 1);
+
+    const NAMES: [&'static str; 7] = ["int", "float", "boolean", "string", "list", "map", "null"];
+
+    pub fn of(value: &Value) -> Type {
+        match value {
+            Value::Null => Type::NULL,
+            Value::Int(_) => Type::INT,
+            Value::Float(_) => Type::FLOAT,
+            Value::Boolean(_) => Type::BOOLEAN,
+            Value::Str(_) => Type::STRING,
+            Value::List(_) => Type::LIST,
+            Value::Map(_) => Type::MAP,
+        }
+    }
+
+    pub fn contains(self, other: Type) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: Type) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub(crate) fn without(self, other: Type) -> Type {
+        Type(self.0 & !other.0)
+    }
+
+    pub(crate) fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub(crate) fn empty() -> Type {
+        Type(0)
+    }
+
+    /// Every single kind in the set.
+    pub(crate) fn kinds(self) -> impl Iterator<Item = Type> {
+        (0..Self::NAMES.len())
+            .map(|i| Type(1 << i))
+            .filter(move |kind| self.contains(*kind))
+    }
+}
+
+impl BitOr for Type {
+    type Output = Type;
+
+    fn bitor(self, rhs: Type) -> Type {
+        Type(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Type {
+    fn bitor_assign(&mut self, rhs: Type) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if *self == Type::ANY {
+            return write!(f, "any");
+        }
+
+        for (i, kind) in self.kinds().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+
+            write!(f, "{}", Self::NAMES[kind.0.trailing_zeros() as usize])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Type({self})")
+    }
+}
@@ -1,13 +1,52 @@
-use crate::parser::BinaryOp;
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use super::{RuntimeErrorKind, Type};
+use crate::parser::{BinaryOp, UnaryOp};
+
+/// How integer arithmetic behaves when the result doesn't fit in an `i64`.
+/// Division by zero is an error whatever the mode.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ArithmeticMode {
+    /// Fail with an integer overflow error.
+    #[default]
+    Checked,
+    /// Wrap around at the bounds of `i64`.
+    Wrapping,
+    /// Clamp to `i64::MIN` or `i64::MAX`.
+    Saturating,
+    /// Redo the operation on floats.
+    PromoteToFloat,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Value {
     Int(i64),
     Float(f64),
     Boolean(bool),
+    Str(Arc<str>),
+    List(Arc<[Value]>),
+    Map(Arc<BTreeMap<Arc<str>, Value>>),
+    Null,
 }
 
 impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Null => "null",
+        }
+    }
+
     pub fn to_float(&self) -> f64 {
         match self {
             Value::Int(v) => *v as f64,
@@ -19,6 +58,8 @@ impl Value {
                     0.0
                 }
             }
+            Value::Str(v) => v.trim().parse().unwrap_or(f64::NAN),
+            Value::List(_) | Value::Map(_) | Value::Null => f64::NAN,
         }
     }
 
@@ -33,6 +74,11 @@ impl Value {
                     0
                 }
             }
+            Value::Str(v) => v
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| Value::Float(self.to_float()).to_int()),
+            Value::List(_) | Value::Map(_) | Value::Null => 0,
         }
     }
 
@@ -41,72 +87,322 @@ impl Value {
             Value::Int(v) => *v != 0,
             Value::Float(v) => !v.is_nan() && *v != 0.0,
             Value::Boolean(v) => *v,
+            Value::Str(v) => !v.is_empty(),
+            Value::List(v) => !v.is_empty(),
+            Value::Map(v) => !v.is_empty(),
+            Value::Null => false,
         }
     }
 
-    pub fn neg(&self) -> Self {
-        match self {
+    pub fn neg(&self, mode: ArithmeticMode) -> Result<Self, RuntimeErrorKind> {
+        Ok(match self {
             Value::Float(v) => Value::Float(-v),
-            Value::Int(v) => Value::Int(-v),
+            Value::Int(v) => match mode {
+                ArithmeticMode::Checked => {
+                    Value::Int(v.checked_neg().ok_or(RuntimeErrorKind::IntegerOverflow)?)
+                }
+                ArithmeticMode::Wrapping => Value::Int(v.wrapping_neg()),
+                ArithmeticMode::Saturating => Value::Int(v.saturating_neg()),
+                ArithmeticMode::PromoteToFloat => v
+                    .checked_neg()
+                    .map_or_else(|| Value::Float(-(*v as f64)), Value::Int),
+            },
             Value::Boolean(v) => Value::Int(if *v { -1 } else { 0 }),
-        }
+            Value::Str(_) | Value::List(_) | Value::Map(_) => {
+                return Err(RuntimeErrorKind::InvalidOperand(
+                    UnaryOp::Neg,
+                    self.type_name(),
+                ))
+            }
+            Value::Null => Value::Null,
+        })
     }
 
+    /// Logical negation of the truthiness, always a boolean.
     pub fn not(&self) -> Self {
-        Value::Int(!self.to_int())
+        Value::Boolean(!self.to_bool())
     }
 
-    pub fn equals(a: Self, b: Self) -> bool {
+    /// Bitwise complement of an int, or of a boolean as `0` or `1`. Floats
+    /// have no bits to flip and are rejected like the type checker does.
+    pub fn bit_not(&self) -> Result<Self, RuntimeErrorKind> {
+        match self {
+            Value::Null => Ok(Value::Null),
+            Value::Int(_) | Value::Boolean(_) => Ok(Value::Int(!self.to_int())),
+            _ => Err(RuntimeErrorKind::InvalidOperand(
+                UnaryOp::BitNot,
+                self.type_name(),
+            )),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Whether arithmetic and bitwise operators take the value as a number,
+    /// strings, lists and maps can only be concatenated.
+    fn is_numeric(&self) -> bool {
+        !matches!(self, Value::Str(_) | Value::List(_) | Value::Map(_))
+    }
+
+    pub fn equals(a: &Self, b: &Self) -> bool {
+        Value::compare(a, b) == Some(Ordering::Equal)
+    }
+
+    /// Orders two values numerically, booleans compare as `0` and `1`, strings
+    /// and lists compare lexicographically with their own kind, maps only
+    /// compare equal or not at all, and null only equals null. Returns `None`
+    /// when either side is NaN or the kinds can't be compared.
+    pub fn compare(a: &Self, b: &Self) -> Option<Ordering> {
         match (a, b) {
-            (Value::Int(a), Value::Int(b)) => a == b,
-            (Value::Float(a), Value::Float(b)) => a == b,
-            (Value::Boolean(a), Value::Boolean(b)) => a == b,
-            (Value::Int(b), Value::Float(a)) | (Value::Float(a), Value::Int(b)) => {
-                a.trunc() == a && a as i64 == b
-            }
-            (Value::Int(a), Value::Boolean(b)) | (Value::Boolean(b), Value::Int(a)) => {
-                a == if b { 1 } else { 0 }
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            (Value::Null, _) | (_, Value::Null) => None,
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            (Value::List(a), Value::List(b)) => {
+                for (a, b) in a.iter().zip(b.iter()) {
+                    match Value::compare(a, b)? {
+                        Ordering::Equal => continue,
+                        ord => return Some(ord),
+                    }
+                }
+
+                Some(a.len().cmp(&b.len()))
             }
-            (Value::Float(a), Value::Boolean(b)) | (Value::Boolean(b), Value::Float(a)) => {
-                a == if b { 1.0 } else { 0.0 }
+            (Value::Map(a), Value::Map(b)) => {
+                let equal = a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|((ka, a), (kb, b))| ka == kb && Value::equals(a, b));
+
+                equal.then_some(Ordering::Equal)
             }
+            (Value::Str(_) | Value::List(_) | Value::Map(_), _)
+            | (_, Value::Str(_) | Value::List(_) | Value::Map(_)) => None,
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Float(a), b) => compare_int_float(b.to_int(), *a).map(Ordering::reverse),
+            (a, Value::Float(b)) => compare_int_float(a.to_int(), *b),
+            (a, b) => Some(a.to_int().cmp(&b.to_int())),
         }
     }
 
-    pub fn do_binary_op(a: Self, b: Self, op: BinaryOp) -> Self {
+    pub fn do_unary_op(&self, op: UnaryOp, mode: ArithmeticMode) -> Result<Self, RuntimeErrorKind> {
         match op {
-            BinaryOp::Add => Value::do_add(a, b),
-            BinaryOp::Sub => Value::do_sub(a, b),
-            BinaryOp::Mul => Value::do_mul(a, b),
-            BinaryOp::Div => Value::do_div(a, b),
-            BinaryOp::Mod => Value::do_mod(a, b),
-            BinaryOp::Equal => Value::equals(a, b).into(),
-            BinaryOp::NotEqual => (!Value::equals(a, b)).into(),
+            UnaryOp::Neg => self.neg(mode),
+            UnaryOp::Not => Ok(self.not()),
+            UnaryOp::BitNot => self.bit_not(),
+        }
+    }
+
+    pub fn do_binary_op(
+        a: Self,
+        b: Self,
+        op: BinaryOp,
+        mode: ArithmeticMode,
+    ) -> Result<Self, RuntimeErrorKind> {
+        Ok(match op {
+            BinaryOp::Add
+            | BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Mod
+            | BinaryOp::Pow
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::ShiftLeft
+            | BinaryOp::ShiftRight
+            | BinaryOp::UnsignedShiftRight
+                if a.is_null() || b.is_null() =>
+            {
+                Value::Null
+            }
+            BinaryOp::NullCoalesce => {
+                if a.is_null() {
+                    b
+                } else {
+                    a
+                }
+            }
+            BinaryOp::Add => match (&a, &b) {
+                (Value::Str(_), _) | (_, Value::Str(_)) => Value::from(format!("{a}{b}")),
+                (Value::List(a), Value::List(b)) => {
+                    Value::List(a.iter().chain(b.iter()).cloned().collect())
+                }
+                _ if !a.is_numeric() || !b.is_numeric() => {
+                    return Err(RuntimeErrorKind::InvalidOperands(
+                        op,
+                        a.type_name(),
+                        b.type_name(),
+                    ))
+                }
+                _ => Value::do_add(a, b, mode)?,
+            },
+            BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Mod
+            | BinaryOp::Pow
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::ShiftLeft
+            | BinaryOp::ShiftRight
+            | BinaryOp::UnsignedShiftRight
+                if !a.is_numeric() || !b.is_numeric() =>
+            {
+                return Err(RuntimeErrorKind::InvalidOperands(
+                    op,
+                    a.type_name(),
+                    b.type_name(),
+                ))
+            }
+            BinaryOp::Sub => Value::do_sub(a, b, mode)?,
+            BinaryOp::Mul => Value::do_mul(a, b, mode)?,
+            BinaryOp::Div => Value::do_div(a, b, mode)?,
+            BinaryOp::Mod => Value::do_mod(a, b, mode)?,
+            BinaryOp::Pow => Value::pow(&a, &b, mode)?,
+            BinaryOp::Equal => Value::equals(&a, &b).into(),
+            BinaryOp::NotEqual => (!Value::equals(&a, &b)).into(),
+            BinaryOp::Less => matches!(Value::compare(&a, &b), Some(Ordering::Less)).into(),
+            BinaryOp::LessEqual => matches!(
+                Value::compare(&a, &b),
+                Some(Ordering::Less | Ordering::Equal)
+            )
+            .into(),
+            BinaryOp::Greater => matches!(Value::compare(&a, &b), Some(Ordering::Greater)).into(),
+            BinaryOp::GreaterEqual => matches!(
+                Value::compare(&a, &b),
+                Some(Ordering::Greater | Ordering::Equal)
+            )
+            .into(),
             BinaryOp::BitAnd => Value::do_bitwise_and(a, b),
             BinaryOp::BitOr => Value::do_bitwise_or(a, b),
             BinaryOp::BitXor => Value::do_bitwise_xor(a, b),
+            BinaryOp::ShiftLeft => Value::do_shift_left(a, b)?,
+            BinaryOp::ShiftRight => Value::do_shift_right(a, b)?,
+            BinaryOp::UnsignedShiftRight => Value::do_unsigned_shift_right(a, b)?,
             BinaryOp::LogicalAnd => Value::do_logical_and(a, b),
             BinaryOp::LogicalOr => Value::do_logical_or(a, b),
+        })
+    }
+}
+
+impl Value {
+    /// Raise `a` to the power of `b`. Integers stay integers unless the exponent
+    /// is negative, in which case the result is a float.
+    pub fn pow(a: &Value, b: &Value, mode: ArithmeticMode) -> Result<Value, RuntimeErrorKind> {
+        match (a, b) {
+            (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+            (Value::Int(a), Value::Int(b)) if *b >= 0 => {
+                // Any base but -1, 0 and 1 overflows long before `u32::MAX`, and
+                // those three only depend on the parity of the exponent
+                let exp = u32::try_from(*b).unwrap_or(u32::MAX - (*b % 2 == 0) as u32);
+                Ok(match mode {
+                    ArithmeticMode::Checked => Value::Int(
+                        a.checked_pow(exp)
+                            .ok_or(RuntimeErrorKind::IntegerOverflow)?,
+                    ),
+                    ArithmeticMode::Wrapping => Value::Int(a.wrapping_pow(exp)),
+                    ArithmeticMode::Saturating => Value::Int(a.saturating_pow(exp)),
+                    ArithmeticMode::PromoteToFloat => a
+                        .checked_pow(exp)
+                        .map_or_else(|| Value::Float((*a as f64).powf(*b as f64)), Value::Int),
+                })
+            }
+            _ => Ok(Value::Float(a.to_float().powf(b.to_float()))),
+        }
+    }
+
+    /// Index into a list, negative indices count from the end, or look up a key
+    /// of a map. Indexing into null gives null.
+    pub fn index(&self, index: &Value) -> Result<Value, RuntimeErrorKind> {
+        match (self, index) {
+            (Value::Null, _) => Ok(Value::Null),
+            (Value::List(list), Value::Int(i)) => {
+                let pos = if *i < 0 {
+                    usize::try_from(i.unsigned_abs())
+                        .ok()
+                        .and_then(|i| list.len().checked_sub(i))
+                } else {
+                    usize::try_from(*i).ok()
+                };
+
+                pos.and_then(|pos| list.get(pos))
+                    .cloned()
+                    .ok_or(RuntimeErrorKind::IndexOutOfBounds(*i, list.len()))
+            }
+            (Value::Map(map), Value::Str(key)) => map
+                .get(key)
+                .cloned()
+                .ok_or_else(|| RuntimeErrorKind::MissingKey(key.to_string())),
+            _ => Err(RuntimeErrorKind::InvalidIndex(
+                self.type_name(),
+                index.type_name(),
+            )),
         }
     }
 }
 
+/// Exact comparison of an `i64` against an `f64`, without rounding the integer
+/// through `f64` first.
+fn compare_int_float(a: i64, b: f64) -> Option<Ordering> {
+    if b.is_nan() {
+        return None;
+    }
+
+    // 2^63, the first float past `i64::MAX`
+    const I64_END: f64 = 9_223_372_036_854_775_808.0;
+    if b >= I64_END {
+        return Some(Ordering::Less);
+    }
+    if b < -I64_END {
+        return Some(Ordering::Greater);
+    }
+
+    let trunc = b.trunc();
+    match a.cmp(&(trunc as i64)) {
+        Ordering::Equal => 0.0.partial_cmp(&(b - trunc)),
+        ord => Some(ord),
+    }
+}
+
 macro_rules! binary_op {
-    (math, $name:ident, $op:tt) => {
-        pub fn $name(a: Self,  b: Self) -> Self {
-            match (a, b) {
-                (Value::Int(a), Value::Int(b)) => Value::Int(a $op b),
+    (math, $name:ident, $op:tt, $checked:ident, $wrapping:ident, $saturating:ident) => {
+        binary_op!(@int false, false, $name, $op, $checked, $wrapping, $saturating);
+    };
+    (division, $name:ident, $op:tt, $checked:ident, $wrapping:ident, $saturating:ident) => {
+        binary_op!(@int true, false, $name, $op, $checked, $wrapping, $saturating);
+    };
+    (remainder, $name:ident, $op:tt, $checked:ident, $wrapping:ident, $saturating:ident) => {
+        binary_op!(@int true, true, $name, $op, $checked, $wrapping, $saturating);
+    };
+    (@int $division:literal, $remainder:literal, $name:ident, $op:tt, $checked:ident, $wrapping:ident, $saturating:ident) => {
+        pub fn $name(a: Self,  b: Self, mode: ArithmeticMode) -> Result<Self, RuntimeErrorKind> {
+            match (&a, &b) {
+                (Value::Int(_), Value::Int(0)) if $division => Err(RuntimeErrorKind::DivisionByZero),
+                (Value::Int(_), Value::Int(-1)) if $remainder => Ok(Value::Int(0)),
+                (Value::Int(a), Value::Int(b)) => Ok(match mode {
+                    ArithmeticMode::Checked => {
+                        Value::Int(a.$checked(*b).ok_or(RuntimeErrorKind::IntegerOverflow)?)
+                    }
+                    ArithmeticMode::Wrapping => Value::Int(a.$wrapping(*b)),
+                    ArithmeticMode::Saturating => Value::Int(a.$saturating(*b)),
+                    ArithmeticMode::PromoteToFloat => a
+                        .$checked(*b)
+                        .map_or_else(|| Value::Float(*a as f64 $op *b as f64), Value::Int),
+                }),
                 _ => {
                     let a = a.to_float();
                     let b = b.to_float();
-                    Value::Float(a $op b)
+                    Ok(Value::Float(a $op b))
                 }
             }
         }
     };
     (bitwise, $name:ident, $op:tt) => {
         pub fn $name(a: Self,  b: Self) -> Self {
-            match (a, b) {
+            match (&a, &b) {
                 (Value::Int(a), Value::Int(b)) => Value::Int(a $op b),
                 _ => {
                     let a = a.to_int();
@@ -116,6 +412,18 @@ macro_rules! binary_op {
             }
         }
     };
+    (shift, $name:ident, $shift:expr) => {
+        pub fn $name(a: Self, b: Self) -> Result<Self, RuntimeErrorKind> {
+            let a = a.to_int();
+            let b = b.to_int();
+            let amount = u32::try_from(b)
+                .ok()
+                .filter(|amount| *amount < i64::BITS)
+                .ok_or(RuntimeErrorKind::InvalidShift(b))?;
+
+            Ok(Value::Int($shift(a, amount)))
+        }
+    };
     (logical, $name:ident, $op:tt) => {
         pub fn $name(a: Self,  b: Self) -> Self {
             let a = a.to_bool();
@@ -127,14 +435,23 @@ macro_rules! binary_op {
 }
 
 impl Value {
-    binary_op!(math, do_add, +);
-    binary_op!(math, do_sub, -);
-    binary_op!(math, do_mul, *);
-    binary_op!(math, do_div, /);
-    binary_op!(math, do_mod, %);
+    binary_op!(math, do_add, +, checked_add, wrapping_add, saturating_add);
+    binary_op!(math, do_sub, -, checked_sub, wrapping_sub, saturating_sub);
+    binary_op!(math, do_mul, *, checked_mul, wrapping_mul, saturating_mul);
+    binary_op!(division, do_div, /, checked_div, wrapping_div, saturating_div);
+    // `i64::MIN % -1` is the only overflowing remainder, like any remainder by
+    // -1 its result is 0 whatever the mode
+    binary_op!(remainder, do_mod, %, checked_rem, wrapping_rem, wrapping_rem);
     binary_op!(bitwise, do_bitwise_and, &);
     binary_op!(bitwise, do_bitwise_or, |);
     binary_op!(bitwise, do_bitwise_xor, ^);
+    binary_op!(shift, do_shift_left, |a: i64, n| a << n);
+    binary_op!(shift, do_shift_right, |a: i64, n| a >> n);
+    binary_op!(
+        shift,
+        do_unsigned_shift_right,
+        |a: i64, n| ((a as u64) >> n) as i64
+    );
     binary_op!(logical, do_logical_and, &&);
     binary_op!(logical, do_logical_or, ||);
 }
@@ -157,12 +474,279 @@ impl From<f64> for Value {
     }
 }
 
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(Arc::from(value))
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(Arc::from(value))
+    }
+}
+
+impl From<Arc<str>> for Value {
+    fn from(value: Arc<str>) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Arc<[Value]>> for Value {
+    fn from(value: Arc<[Value]>) -> Self {
+        Value::List(value)
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Map(Arc::new(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        ))
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(value: BTreeMap<K, V>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> From<HashMap<K, V>> for Value {
+    fn from(value: HashMap<K, V>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl From<Value> for Vec<Value> {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::List(v) => v.to_vec(),
+            v => vec![v],
+        }
+    }
+}
+
+impl From<Value> for String {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Str(v) => v.to_string(),
+            v => v.to_string(),
+        }
+    }
+}
+
+impl From<Value> for Arc<str> {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Str(v) => v,
+            v => Arc::from(v.to_string()),
+        }
+    }
+}
+
+/// Strict conversion of an argument into a parameter type of an external
+/// function. Unlike `From<Value>`, no coercion happens besides widening an int
+/// to a float, a mismatch is reported to the caller instead.
+pub trait TryFromValue: Sized {
+    /// Name of the accepted type, used in error messages.
+    fn type_name() -> Cow<'static, str>;
+
+    /// Kinds of values that may convert, used by the type checker.
+    fn value_type() -> Type;
+
+    fn try_from_value(value: Value) -> Option<Self>;
+}
+
+impl TryFromValue for Value {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Borrowed("any")
+    }
+
+    fn value_type() -> Type {
+        Type::ANY
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl TryFromValue for i64 {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Borrowed("int")
+    }
+
+    fn value_type() -> Type {
+        Type::INT
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl TryFromValue for f64 {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Borrowed("float")
+    }
+
+    fn value_type() -> Type {
+        Type::NUMBER
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Int(v) => Some(v as f64),
+            Value::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl TryFromValue for bool {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Borrowed("boolean")
+    }
+
+    fn value_type() -> Type {
+        Type::BOOLEAN
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Boolean(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl TryFromValue for String {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Borrowed("string")
+    }
+
+    fn value_type() -> Type {
+        Type::STRING
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Str(v) => Some(v.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl TryFromValue for Arc<str> {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Borrowed("string")
+    }
+
+    fn value_type() -> Type {
+        Type::STRING
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Str(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl<T: TryFromValue> TryFromValue for Vec<T> {
+    fn type_name() -> Cow<'static, str> {
+        Cow::Owned(format!("list of {}", T::type_name()))
+    }
+
+    fn value_type() -> Type {
+        Type::LIST
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::List(v) => v.iter().cloned().map(T::try_from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// `null` converts to `None`, anything else must convert to `T`.
+impl<T: TryFromValue> TryFromValue for Option<T> {
+    fn type_name() -> Cow<'static, str> {
+        T::type_name()
+    }
+
+    fn value_type() -> Type {
+        T::value_type() | Type::NULL
+    }
+
+    fn try_from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            v => T::try_from_value(v).map(Some),
+        }
+    }
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Int(v) => write!(f, "{}", v),
             Value::Float(v) => write!(f, "{}", v),
             Value::Boolean(v) => write!(f, "{}", v),
+            Value::Str(v) => write!(f, "{}", v),
+            Value::List(v) => {
+                write!(f, "[")?;
+                for (i, item) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write_nested(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(v) => {
+                write!(f, "{{")?;
+                for (i, (key, item)) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}: ", key)?;
+                    write_nested(f, item)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Null => write!(f, "null"),
         }
     }
 }
+
+/// Strings nested in a list or a map are quoted to keep them apart from the
+/// surrounding punctuation.
+fn write_nested(f: &mut std::fmt::Formatter<'_>, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::Str(s) => write!(f, "\"{}\"", s.escape_debug()),
+        value => write!(f, "{}", value),
+    }
+}
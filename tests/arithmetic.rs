@@ -0,0 +1,149 @@
+use expr::{
+    eval_with_context, eval_with_registry, ArithmeticMode, Context, Error, Registry,
+    RuntimeErrorKind, Type, Value,
+};
+
+const MODES: [ArithmeticMode; 4] = [
+    ArithmeticMode::Checked,
+    ArithmeticMode::Wrapping,
+    ArithmeticMode::Saturating,
+    ArithmeticMode::PromoteToFloat,
+];
+
+fn registry(mode: ArithmeticMode) -> Registry {
+    let mut registry = Registry::default();
+    registry.set_arithmetic(mode);
+    registry
+}
+
+#[test]
+fn pow_follows_the_arithmetic_mode() {
+    for mode in MODES {
+        let registry = registry(mode);
+        for (a, b) in [(2, 64), (-3, 41), (7, 3), (2, -1)] {
+            let call = eval_with_registry(&registry, &format!("pow({a}, {b})"));
+            let op = eval_with_registry(&registry, &format!("({a}) ** {b}"));
+            match (call, op) {
+                (Ok(call), Ok(op)) => assert_eq!(call, op, "{mode:?} {a} ** {b}"),
+                (Err(Error::RuntimeError(call)), Err(Error::RuntimeError(op))) => {
+                    assert_eq!(call.to_string(), op.to_string(), "{mode:?} {a} ** {b}")
+                }
+                (call, op) => panic!("{mode:?} {a} ** {b}: {call:?} and {op:?}"),
+            }
+        }
+    }
+
+    let ret = eval_with_registry(&registry(ArithmeticMode::Wrapping), "pow(2, 64)").unwrap();
+    assert_eq!(ret, Value::Int(0));
+    let err = eval_with_registry(&registry(ArithmeticMode::Checked), "pow(2, 64)").unwrap_err();
+    assert!(matches!(
+        err,
+        Error::RuntimeError(err) if matches!(err.kind(), RuntimeErrorKind::IntegerOverflow)
+    ));
+}
+
+#[test]
+fn remainder_by_minus_one_is_zero_in_every_mode() {
+    for mode in MODES {
+        let mut registry = registry(mode);
+        let min = registry.set_var(b"min", i64::MIN).unwrap();
+        let mut ctx = Context::new(&registry);
+        ctx.set_var(min, i64::MIN).unwrap();
+
+        for src in ["(-9223372036854775807 - 1) % -1", "min % -1", "7 % -1"] {
+            let ret = eval_with_context(&registry, &ctx, src).unwrap();
+            assert_eq!(ret, Value::Int(0), "{mode:?} {src}");
+        }
+    }
+}
+
+/// Outcome in each of `MODES`, errors by their message.
+type Expected = [Result<Value, &'static str>; 4];
+
+const OVERFLOW: &str = "Integer overflow";
+const DIVISION_BY_ZERO: &str = "Division by zero";
+
+/// Source of an int literal, `i64::MIN` has none.
+fn literal(v: i64) -> String {
+    match v {
+        i64::MIN => format!("({} - 1)", i64::MIN + 1),
+        v => v.to_string(),
+    }
+}
+
+/// Evaluate `template` with `{a}` and `{b}` replaced by literals, which the
+/// compiler folds, and by variables, which are only known when running.
+fn eval_both_ways(
+    mode: ArithmeticMode,
+    template: &str,
+    a: i64,
+    b: i64,
+) -> [Result<Value, String>; 2] {
+    let mut registry = registry(mode);
+    let va = registry.declare_var(b"a", Type::INT).unwrap();
+    let vb = registry.declare_var(b"b", Type::INT).unwrap();
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(va, a).unwrap();
+    ctx.set_var(vb, b).unwrap();
+
+    let folded = template
+        .replace("{a}", &literal(a))
+        .replace("{b}", &literal(b));
+    let runtime = template.replace("{a}", "a").replace("{b}", "b");
+    [folded, runtime].map(|src| {
+        eval_with_context(&registry, &ctx, &src).map_err(|err| match err {
+            Error::RuntimeError(err) => err.to_string(),
+            err => panic!("{src}: {err:?}"),
+        })
+    })
+}
+
+#[test]
+fn folding_and_running_agree_in_every_mode() {
+    let promoted = |v: f64| Ok(Value::Float(v));
+    #[rustfmt::skip]
+    let cases: [(&str, i64, i64, Expected); 9] = [
+        ("{a} + {b}", i64::MAX, 1, [
+            Err(OVERFLOW), Ok(Value::Int(i64::MIN)), Ok(Value::Int(i64::MAX)), promoted(i64::MAX as f64 + 1.0),
+        ]),
+        ("{a} - {b}", i64::MIN, 1, [
+            Err(OVERFLOW), Ok(Value::Int(i64::MAX)), Ok(Value::Int(i64::MIN)), promoted(i64::MIN as f64 - 1.0),
+        ]),
+        ("{a} * {b}", i64::MAX, 2, [
+            Err(OVERFLOW), Ok(Value::Int(-2)), Ok(Value::Int(i64::MAX)), promoted(i64::MAX as f64 * 2.0),
+        ]),
+        ("{a} / {b}", i64::MIN, -1, [
+            Err(OVERFLOW), Ok(Value::Int(i64::MIN)), Ok(Value::Int(i64::MAX)), promoted(-(i64::MIN as f64)),
+        ]),
+        ("-{a}", i64::MIN, 0, [
+            Err(OVERFLOW), Ok(Value::Int(i64::MIN)), Ok(Value::Int(i64::MAX)), promoted(-(i64::MIN as f64)),
+        ]),
+        ("{a} ** {b}", 2, 63, [
+            Err(OVERFLOW), Ok(Value::Int(i64::MIN)), Ok(Value::Int(i64::MAX)), promoted(2f64.powi(63)),
+        ]),
+        ("{a} + {b}", 40, 2, [
+            Ok(Value::Int(42)), Ok(Value::Int(42)), Ok(Value::Int(42)), Ok(Value::Int(42)),
+        ]),
+        ("{a} / {b}", 1, 0, [
+            Err(DIVISION_BY_ZERO), Err(DIVISION_BY_ZERO), Err(DIVISION_BY_ZERO), Err(DIVISION_BY_ZERO),
+        ]),
+        ("{a} % {b}", 1, 0, [
+            Err(DIVISION_BY_ZERO), Err(DIVISION_BY_ZERO), Err(DIVISION_BY_ZERO), Err(DIVISION_BY_ZERO),
+        ]),
+    ];
+
+    for (template, a, b, expected) in cases {
+        for (mode, expected) in MODES.into_iter().zip(expected) {
+            let expected = expected.map_err(str::to_string);
+            let [folded, runtime] = eval_both_ways(mode, template, a, b);
+            assert_eq!(
+                folded, expected,
+                "{mode:?} {template} with literals {a}, {b}"
+            );
+            assert_eq!(
+                runtime, expected,
+                "{mode:?} {template} with variables {a}, {b}"
+            );
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use expr::{Column, Context, Expr, Program, Registry, RuntimeErrorKind, Type, Value, VarHandle};
+
+const ROWS: usize = 5;
+const INTS: [i64; ROWS] = [-3, 0, 2, 7, 9];
+/// Ints without a float of the same value: the last row is past 2^53, where
+/// ints and floats don't compare the same, and `i64::MIN` has no `abs`.
+const LARGE_INTS: [i64; ROWS] = [i64::MIN, 0, 2, 7, (1 << 53) + 1];
+const FLOATS: [f64; ROWS] = [1.5, -2.0, 0.0, 7.0, (1u64 << 53) as f64];
+
+struct Setup {
+    registry: Registry,
+    i: VarHandle,
+    x: VarHandle,
+}
+
+fn setup() -> Setup {
+    let mut registry = Registry::default();
+    let i = registry.declare_var(b"i", Type::INT).unwrap();
+    let x = registry.declare_var(b"x", Type::FLOAT).unwrap();
+    Setup { registry, i, x }
+}
+
+/// Run `src` over `ints` and `FLOATS` once as a batch and once row by row,
+/// errors by their message.
+fn both_ways(src: &str, ints: &[i64; ROWS]) -> [Result<Vec<Value>, String>; 2] {
+    let Setup { registry, i, x } = setup();
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    let ctx = Context::new(&registry);
+
+    let mut out = vec![Value::Null; ROWS];
+    let columns = [(i, Column::Int(ints)), (x, Column::Float(&FLOATS))];
+    let batch = program
+        .run_batch(&registry, &ctx, &columns, &mut out)
+        .map(|()| out)
+        .map_err(|err| err.to_string());
+
+    let rows = (0..ROWS)
+        .map(|row| {
+            let mut ctx = ctx.clone();
+            ctx.set_var(i, ints[row])
+                .and_then(|ctx| ctx.set_var(x, FLOATS[row]))
+                .unwrap();
+            program.run(&registry, &ctx)
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|err| err.to_string());
+
+    [batch, rows]
+}
+
+fn assert_same(sources: &[&str]) {
+    for ints in [&INTS, &LARGE_INTS] {
+        for src in sources {
+            // Compared by their debug output, which is the same for two NaNs
+            let [batch, rows] = both_ways(src, ints).map(|ret| format!("{ret:?}"));
+            assert_eq!(batch, rows, "{src} with {ints:?}");
+        }
+    }
+}
+
+#[test]
+fn float_columns_match_running_each_row() {
+    assert_same(&[
+        "x * 2 + 1",
+        "x - 0.5",
+        "x / 0",
+        "x % 2",
+        "x ** 2",
+        "-x",
+        "x < 1",
+        "x == 7",
+        "x >= 0.0",
+    ]);
+}
+
+#[test]
+fn int_columns_stay_ints() {
+    assert_same(&["i * 2", "i / 2", "i % 4", "i - i", "-i", "i > 0", "[i, 1]"]);
+
+    let [batch, _] = both_ways("i / 2", &INTS);
+    let halves = INTS.iter().map(|v| Value::Int(v / 2)).collect::<Vec<_>>();
+    assert_eq!(batch.unwrap(), halves);
+}
+
+#[test]
+fn mixed_int_and_float_columns_match_running_each_row() {
+    assert_same(&[
+        "i + x",
+        "x * i",
+        "i + 0.5",
+        "i / 2.0",
+        "i == x",
+        "i < x",
+        "x > i",
+        "i ** 0.5",
+        "x + i * 2",
+    ]);
+}
+
+#[test]
+fn programs_with_jumps_match_running_each_row() {
+    assert_same(&[
+        "x > 0 && i > 1",
+        "x > 0 || i > 1",
+        "i > 0 ? x : i",
+        "(i > 5 ? null : i) ?? x",
+        "fn f(a) = a * 2; f(x) + f(i)",
+        "map([x, i], |a| a + 1)",
+    ]);
+}
+
+#[test]
+fn failing_rows_fail_the_batch() {
+    for src in [
+        "i / (i - 2)",
+        "10 % i",
+        "i * i * i * i * i",
+        "i > 5 ? i / 0 : 1",
+    ] {
+        let [batch, rows] = both_ways(src, &LARGE_INTS);
+        assert!(batch.is_err(), "{src}: {batch:?}");
+        assert_eq!(batch, rows, "{src}");
+    }
+}
+
+#[test]
+fn columns_of_another_length_fail_the_batch() {
+    let Setup { registry, i, x } = setup();
+    let expr = Expr::from_src(b"i + x").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+
+    let mut out = vec![Value::Null; ROWS];
+    let columns = [(i, Column::Int(&INTS)), (x, Column::Float(&FLOATS[..3]))];
+    let err = program
+        .run_batch(&registry, &Context::new(&registry), &columns, &mut out)
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::ColumnLength {
+            expected: ROWS,
+            got: 3
+        }
+    ));
+    assert!(out.iter().all(|v| *v == Value::Null));
+}
@@ -0,0 +1,68 @@
+use expr::{eval, Error, RuntimeErrorKind, Value};
+
+fn eval_ok(src: &str) -> Value {
+    eval(src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+#[test]
+fn empty_aggregates() {
+    for src in ["sum()", "sum([])", "sum([], [])"] {
+        let ret = eval_ok(src);
+        assert!(
+            matches!(ret, Value::Float(v) if v == 0.0 && v.is_sign_positive()),
+            "{src}: {ret:?}"
+        );
+        assert_eq!(ret.to_string(), "0");
+    }
+
+    for src in ["max([])", "min([])", "max([], [])"] {
+        assert_eq!(eval_ok(src), Value::Null, "{src}");
+    }
+}
+
+#[test]
+fn aggregates_expand_lists() {
+    assert_eq!(eval_ok("sum(1, [2, 3], 4.5)"), Value::Float(10.5));
+    assert_eq!(eval_ok("max(1, [7, 3], -2)"), Value::Float(7.0));
+    assert_eq!(eval_ok("min([1, 7], 3, -2)"), Value::Float(-2.0));
+    assert_eq!(eval_ok("max(-5)"), Value::Float(-5.0));
+}
+
+#[test]
+fn aggregates_of_nulls_are_null() {
+    for src in [
+        "sum(null)",
+        "sum([null, null])",
+        "max(null)",
+        "max([null], null)",
+        "min(null)",
+        "min([null])",
+    ] {
+        assert_eq!(eval_ok(src), Value::Null, "{src}");
+    }
+
+    assert_eq!(eval_ok("sum([1, null], null)"), Value::Float(1.0));
+    assert_eq!(eval_ok("max([null, 3], null)"), Value::Float(3.0));
+    assert_eq!(eval_ok("min(null, 2)"), Value::Float(2.0));
+}
+
+#[test]
+fn aggregates_only_take_numbers() {
+    for (src, got) in [
+        ("sum('a')", "string"),
+        ("sum('5')", "string"),
+        ("sum(true, 1)", "boolean"),
+        ("sum([1, [2]])", "list"),
+        ("max('a', 1)", "string"),
+        ("max(1, [false])", "boolean"),
+        ("min(null, '1')", "string"),
+    ] {
+        match eval(src) {
+            Err(Error::RuntimeError(err)) => assert!(
+                matches!(err.kind(), RuntimeErrorKind::InvalidArgument("number", g) if *g == got),
+                "{src}: {err:?}"
+            ),
+            ret => panic!("{src}: expected a runtime error but got {ret:?}"),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use expr::{eval, eval_with_context, Context, Registry, Type, Value};
+
+const OPS: [&str; 6] = ["<", "<=", ">", ">=", "==", "!="];
+
+/// Compare the literals `a` and `b` with every operator, once folded by the
+/// compiler and once through variables, and return the results in the order
+/// of `OPS`.
+fn compare(a: &str, b: &str) -> [bool; 6] {
+    let mut registry = Registry::default();
+    let va = registry.declare_var(b"a", Type::ANY).unwrap();
+    let vb = registry.declare_var(b"b", Type::ANY).unwrap();
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(va, eval(a).unwrap())
+        .unwrap()
+        .set_var(vb, eval(b).unwrap())
+        .unwrap();
+
+    OPS.map(|op| {
+        let [folded, runtime] = [format!("{a} {op} {b}"), format!("a {op} b")].map(|src| {
+            match eval_with_context(&registry, &ctx, &src) {
+                Ok(Value::Boolean(v)) => v,
+                ret => panic!("{src}: {ret:?}"),
+            }
+        });
+        assert_eq!(folded, runtime, "{a} {op} {b}");
+        folded
+    })
+}
+
+const LESS: [bool; 6] = [true, true, false, false, false, true];
+const EQUAL: [bool; 6] = [false, true, false, true, true, false];
+const GREATER: [bool; 6] = [false, false, true, true, false, true];
+const UNORDERED: [bool; 6] = [false, false, false, false, false, true];
+
+fn assert_order(cases: &[(&str, &str, [bool; 6])]) {
+    for (a, b, expected) in cases {
+        assert_eq!(compare(a, b), *expected, "{a} against {b}");
+        // Swapping the sides mirrors the result
+        let [lt, le, gt, ge, eq, ne] = *expected;
+        assert_eq!(compare(b, a), [gt, ge, lt, le, eq, ne], "{b} against {a}");
+    }
+}
+
+#[test]
+fn ints_and_floats_compare_exactly_past_2_pow_53() {
+    assert_order(&[
+        ("9007199254740993", "9007199254740992.0", GREATER),
+        ("9007199254740992", "9007199254740992.0", EQUAL),
+        ("9007199254740991", "9007199254740992.0", LESS),
+        ("-9007199254740993", "-9007199254740992.0", LESS),
+        // 2^63 is past `i64::MAX` and -2^63 is `i64::MIN`
+        ("9223372036854775807", "9223372036854775808.0", LESS),
+        (
+            "(-9223372036854775807 - 1)",
+            "-9223372036854775808.0",
+            EQUAL,
+        ),
+        (
+            "(-9223372036854775807 - 1)",
+            "-10000000000000000000.0",
+            GREATER,
+        ),
+        ("0", "100000000000000000000000.0", LESS),
+    ]);
+}
+
+#[test]
+fn nan_is_unordered_with_everything() {
+    assert_order(&[
+        ("(0.0 / 0.0)", "1", UNORDERED),
+        ("(0.0 / 0.0)", "1.5", UNORDERED),
+        ("(0.0 / 0.0)", "(0.0 / 0.0)", UNORDERED),
+        ("(0.0 / 0.0)", "true", UNORDERED),
+        ("(0.0 / 0.0)", "9223372036854775807", UNORDERED),
+    ]);
+}
+
+#[test]
+fn negative_fractions_compare_against_ints() {
+    assert_order(&[
+        ("-1", "-1.5", GREATER),
+        ("-2", "-1.5", LESS),
+        ("-1", "-0.5", LESS),
+        ("0", "-0.5", GREATER),
+        ("0", "-0.0", EQUAL),
+        ("-1", "-1.0", EQUAL),
+        ("-3", "-2.999999", LESS),
+    ]);
+}
+
+#[test]
+fn booleans_compare_as_zero_and_one() {
+    assert_order(&[
+        ("true", "1", EQUAL),
+        ("true", "1.0", EQUAL),
+        ("false", "0", EQUAL),
+        ("false", "-0.0", EQUAL),
+        ("true", "0.5", GREATER),
+        ("false", "0.1", LESS),
+        ("true", "2", LESS),
+        ("false", "-1", GREATER),
+        ("true", "false", GREATER),
+    ]);
+}
@@ -0,0 +1,148 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use expr::{Context, Expr, Program, Registry, RegistryError, SharedRegistry, Type, Value};
+
+const THREADS: i64 = 8;
+const RUNS: i64 = 1000;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn shared_types_are_send_and_sync() {
+    assert_send_sync::<Program>();
+    assert_send_sync::<SharedRegistry>();
+    assert_send_sync::<Context>();
+    assert_send_sync::<Value>();
+}
+
+#[test]
+fn one_program_from_many_threads() {
+    let mut registry = Registry::default();
+    let x = registry.declare_var(b"x", Type::INT).unwrap();
+    registry
+        .add_var(b"offset", 10)
+        .add_shared_fn(b"double", |v: i64| v * 2);
+
+    let expr = Expr::from_src(b"double(x) + offset + len([x, 'a'])").unwrap();
+    let program = Arc::new(Program::compile(&registry, &expr).unwrap());
+    let registry = Arc::new(registry.into_shared().unwrap());
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let program = Arc::clone(&program);
+            let registry = Arc::clone(&registry);
+            thread::spawn(move || {
+                let mut ctx = Context::new(&registry);
+                for i in 0..RUNS {
+                    let v = t * RUNS + i;
+                    ctx.set_var(x, v).unwrap();
+                    let ret = program.run(&registry, &ctx).unwrap();
+                    assert_eq!(ret.to_int(), v * 2 + 12);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn local_functions_keep_the_registry_on_its_thread() {
+    let calls = Rc::new(Cell::new(0));
+    let mut registry = Registry::default();
+    registry.add_fn(b"tick", {
+        let calls = Rc::clone(&calls);
+        move || {
+            calls.set(calls.get() + 1);
+            calls.get()
+        }
+    });
+
+    let expr = Expr::from_src(b"tick() + tick()").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    let ret = program.run(&registry, &Context::new(&registry)).unwrap();
+    assert_eq!(ret, Value::Int(3));
+    assert_eq!(calls.get(), 2);
+
+    assert!(matches!(
+        registry.into_shared(),
+        Err(RegistryError::UnsharedFunction(name)) if name == "tick"
+    ));
+}
+
+#[test]
+fn shared_functions_keep_state_behind_a_lock() {
+    let calls = Arc::new(AtomicI64::new(0));
+    let mut registry = Registry::default();
+    let local = Mutex::new(0);
+    let counter = Arc::clone(&calls);
+    registry
+        .add_shared_fn(b"tick", move || {
+            let mut local = local.lock().unwrap();
+            *local += 1;
+            *local
+        })
+        .add_shared_fn(b"count", move || counter.fetch_add(1, Ordering::Relaxed));
+
+    let expr = Expr::from_src(b"[tick(), count()]").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    let registry = registry.into_shared().unwrap();
+
+    let mut ticks: Vec<i64> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                scope.spawn(|| {
+                    let ctx = Context::new(&registry);
+                    (0..RUNS)
+                        .map(|_| {
+                            let ret = program.run(&registry, &ctx).unwrap();
+                            Vec::<Value>::from(ret)[0].to_int()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    ticks.sort_unstable();
+    assert_eq!(ticks, (1..=THREADS * RUNS).collect::<Vec<_>>());
+    assert_eq!(calls.load(Ordering::Relaxed), THREADS * RUNS);
+}
+
+#[test]
+fn thawed_registries_keep_their_declarations() {
+    let mut registry = Registry::default();
+    let x = registry.set_var(b"x", 20).unwrap();
+    registry.add_shared_fn(b"double", |v: i64| v * 2);
+
+    let expr = Expr::from_src(b"double(x) + 2").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    let shared = registry.into_shared().unwrap();
+    let ctx = Context::new(&shared);
+    assert_eq!(program.run(&shared, &ctx).unwrap(), Value::Int(42));
+
+    // The handles and contexts of the shared registry stay valid
+    let mut registry = shared.into_inner();
+    registry.add_fn(b"triple", |v: i64| v * 3);
+    assert_eq!(registry.var_handle(b"x"), Some(x));
+    assert_eq!(program.run(&registry, &ctx).unwrap(), Value::Int(42));
+
+    let expr = Expr::from_src(b"triple(x) + double(1)").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    assert_eq!(program.run(&registry, &ctx).unwrap(), Value::Int(62));
+}
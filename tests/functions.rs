@@ -0,0 +1,185 @@
+use expr::{
+    eval, Error, Expr, ParseErrorKind, Program, Registry, RuntimeError, RuntimeErrorKind,
+    TypeError, TypeErrorKind, Value,
+};
+
+fn eval_ok(src: &str) -> Value {
+    eval(src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+fn eval_err(src: &str) -> RuntimeError {
+    match eval(src) {
+        Err(Error::RuntimeError(err)) => err,
+        ret => panic!("{src}: expected a runtime error but got {ret:?}"),
+    }
+}
+
+fn check_err(src: &str) -> TypeError {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    let mut errors = Program::check(&Registry::default(), &expr).unwrap_err();
+    assert_eq!(errors.len(), 1, "{src}: {errors:?}");
+    errors.pop().unwrap()
+}
+
+#[test]
+fn functions_are_called_with_their_arguments() {
+    assert_eq!(eval_ok("fn sq(v) = v * v; sq(3) + sq(4)"), Value::Int(25));
+    assert_eq!(eval_ok("fn sub(a, b) = a - b; sub(10, 3)"), Value::Int(7));
+    assert_eq!(eval_ok("fn seven() = 7; seven() * seven()"), Value::Int(49));
+    assert_eq!(
+        eval_ok("fn sign(v) = v < 0 ? -1 : 1; [sign(-5), sign(5)]"),
+        Value::List(vec![Value::Int(-1), Value::Int(1)].into())
+    );
+}
+
+#[test]
+fn functions_call_the_functions_defined_before_them() {
+    assert_eq!(
+        eval_ok("fn inc(a) = a + 1; fn twice(a) = inc(inc(a)); twice(1) + inc(0)"),
+        Value::Int(4)
+    );
+    assert_eq!(
+        eval_ok("fn outer(a) = (fn inner(b) = a + b; inner(1)); outer(2) + outer(3)"),
+        Value::Int(7)
+    );
+}
+
+#[test]
+fn nested_calls_compile_once_per_function() {
+    // Every function calls the previous one twice, inlining the calls would
+    // double the code at every level
+    let mut src = String::from("fn f0(x) = x; ");
+    for i in 1..=24 {
+        src += &format!("fn f{i}(x) = f{prev}(x) + f{prev}(x); ", prev = i - 1);
+    }
+
+    assert_eq!(eval_ok(&(src.clone() + "f10(1)")), Value::Int(1024));
+    assert_eq!(eval_ok(&(src + "1")), Value::Int(1));
+}
+
+#[test]
+fn let_bindings_are_scoped_to_their_body() {
+    assert_eq!(
+        eval_ok("let a = 1 in let a = a + 1 in a * 10"),
+        Value::Int(20)
+    );
+    assert_eq!(
+        eval_ok("(let a = 1 in a) + (let a = 2 in a)"),
+        Value::Int(3)
+    );
+    assert!(matches!(
+        eval_err("(let a = 1 in a) + a").kind(),
+        RuntimeErrorKind::UndeclaredVariable(name) if name == "a"
+    ));
+}
+
+#[test]
+fn functions_see_the_scope_of_their_definition() {
+    assert_eq!(
+        eval_ok("let k = 2 in fn scale(v) = v * k; let k = 10 in scale(k)"),
+        Value::Int(20)
+    );
+    assert_eq!(
+        eval_ok("let v = 1 in fn id(v) = v; id(5) + v"),
+        Value::Int(6)
+    );
+    assert!(matches!(
+        eval_err("fn f(a) = a; a").kind(),
+        RuntimeErrorKind::UndeclaredVariable(name) if name == "a"
+    ));
+    assert!(matches!(
+        eval_err("(fn f(a) = a; f(1)) + f(2)").kind(),
+        RuntimeErrorKind::UndeclaredFunction(name) if name == "f"
+    ));
+}
+
+#[test]
+fn calls_with_the_wrong_number_of_arguments() {
+    let err = eval_err("fn f(a, b) = a + b; f(1)");
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::WrongArgumentCount(2, 1)
+    ));
+    assert_eq!(err.span().map(|span| span.from), Some(20));
+
+    let err = eval_err("fn f() = 1; f(1)");
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::WrongArgumentCount(0, 1)
+    ));
+
+    assert!(matches!(
+        check_err("fn f(a, b) = a + b; f(1)").kind(),
+        TypeErrorKind::WrongArgumentCount(2, 1)
+    ));
+}
+
+#[test]
+fn duplicate_parameters_are_rejected() {
+    for src in ["fn f(a, a) = a; f(1, 2)", "map([1], |a, a| a)"] {
+        match eval(src) {
+            Err(Error::ParseError(err)) => assert!(
+                matches!(err.kind(), ParseErrorKind::DuplicateParameter(name) if name == "a"),
+                "{src}: {err:?}"
+            ),
+            ret => panic!("{src}: expected a parse error but got {ret:?}"),
+        }
+    }
+}
+
+#[test]
+fn redefined_functions_are_rejected() {
+    let src = "fn sq(v) = v * v; fn sq(v) = v; sq(2)";
+    let err = eval_err(src);
+    assert!(matches!(err.kind(), RuntimeErrorKind::RedefinedFunction(name) if name == "sq"));
+    assert_eq!(err.span().map(|span| span.from), Some(18));
+    assert!(
+        matches!(check_err(src).kind(), TypeErrorKind::RedefinedFunction(name) if name == "sq")
+    );
+
+    let src = "fn f(a) = a; fn g(b) = (fn f(c) = c; f(b)); g(1)";
+    assert!(
+        matches!(eval_err(src).kind(), RuntimeErrorKind::RedefinedFunction(name) if name == "f")
+    );
+}
+
+#[test]
+fn recursion_is_rejected() {
+    for src in [
+        "fn f(n) = n <= 0 ? 0 : f(n - 1); f(3)",
+        "fn f(n) = (fn g(m) = f(m); g(n)); f(3)",
+    ] {
+        assert!(
+            matches!(eval_err(src).kind(), RuntimeErrorKind::RecursiveFunction(name) if name == "f"),
+            "{src}"
+        );
+        assert!(
+            matches!(check_err(src).kind(), TypeErrorKind::RecursiveFunction(name) if name == "f"),
+            "{src}"
+        );
+    }
+}
+
+#[test]
+fn forward_references_are_rejected() {
+    for src in [
+        "fn f(n) = g(n); fn g(n) = n; f(1)",
+        "let a = g(1) in fn g(n) = n; a",
+        "fn f(n) = g(n); let a = 1 in fn g(n) = n; f(a)",
+    ] {
+        assert!(
+            matches!(
+                eval_err(src).kind(),
+                RuntimeErrorKind::FunctionUsedBeforeDefinition(name) if name == "g"
+            ),
+            "{src}"
+        );
+        assert!(
+            matches!(
+                check_err(src).kind(),
+                TypeErrorKind::FunctionUsedBeforeDefinition(name) if name == "g"
+            ),
+            "{src}"
+        );
+    }
+}
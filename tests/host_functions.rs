@@ -0,0 +1,204 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error as _,
+    fmt,
+};
+
+use expr::{
+    eval_with_registry, Error, Expr, Program, Registry, RuntimeError, RuntimeErrorKind, Type, Value,
+};
+
+fn eval_ok(registry: &Registry, src: &str) -> Value {
+    eval_with_registry(registry, src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+fn eval_err(registry: &Registry, src: &str) -> RuntimeError {
+    match eval_with_registry(registry, src) {
+        Err(Error::RuntimeError(err)) => err,
+        ret => panic!("{src}: expected a runtime error but got {ret:?}"),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct NegativeRoot(f64);
+
+impl fmt::Display for NegativeRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} has no real root", self.0)
+    }
+}
+
+impl std::error::Error for NegativeRoot {}
+
+fn root(v: f64) -> Result<f64, NegativeRoot> {
+    match v {
+        v if v < 0.0 => Err(NegativeRoot(v)),
+        v => Ok(v.sqrt()),
+    }
+}
+
+#[test]
+fn failing_functions_abort_with_their_error() {
+    let mut registry = Registry::default();
+    registry.add_fn(b"root", root);
+
+    assert_eq!(eval_ok(&registry, "root(16) + 1"), Value::Float(5.0));
+
+    let err = eval_err(&registry, "1 + root(-4)");
+    match err.kind() {
+        RuntimeErrorKind::ExternalFunction(name, inner) => {
+            assert_eq!(name, "root");
+            assert_eq!(inner.downcast_ref(), Some(&NegativeRoot(-4.0)));
+        }
+        kind => panic!("expected an external function error but got {kind:?}"),
+    }
+    assert_eq!(err.span().map(|span| span.from), Some(4));
+    assert_eq!(
+        err.kind().to_string(),
+        "Function root failed: -4 has no real root"
+    );
+
+    let source = err
+        .source()
+        .and_then(|err| err.downcast_ref::<NegativeRoot>());
+    assert_eq!(source, Some(&NegativeRoot(-4.0)));
+}
+
+#[test]
+fn skipped_calls_cannot_fail() {
+    let mut registry = Registry::default();
+    registry.add_fn(b"root", root);
+
+    assert_eq!(
+        eval_ok(&registry, "false && root(-1) > 0"),
+        Value::Boolean(false)
+    );
+    assert_eq!(eval_ok(&registry, "2 ?? root(-1)"), Value::Int(2));
+}
+
+fn mismatch(err: &RuntimeError) -> (&str, u32, &str, &str) {
+    match err.kind() {
+        RuntimeErrorKind::ArgumentTypeMismatch {
+            function,
+            index,
+            expected,
+            got,
+        } => (function, *index, expected, got),
+        kind => panic!("expected an argument type mismatch but got {kind:?}"),
+    }
+}
+
+#[test]
+fn arguments_of_the_wrong_type_are_rejected() {
+    let mut registry = Registry::default();
+    registry
+        .add_fn(b"root", root)
+        .add_fn(b"twice", |v: i64| v * 2)
+        .add_fn(
+            b"pick",
+            |c: bool, a: String, b: String| if c { a } else { b },
+        );
+
+    // An int widens to a float, nothing else is coerced
+    assert_eq!(eval_ok(&registry, "root(4)"), Value::Float(2.0));
+    assert_eq!(eval_ok(&registry, "twice(21)"), Value::Int(42));
+    assert_eq!(
+        eval_ok(&registry, "pick(false, 'a', 'b')"),
+        Value::from("b")
+    );
+
+    let err = eval_err(&registry, "twice(1.5)");
+    assert_eq!(mismatch(&err), ("twice", 0, "int", "float"));
+    assert_eq!(err.span().map(|span| (span.from, span.to)), Some((6, 8)));
+
+    let err = eval_err(&registry, "pick(1, 'a', 'b')");
+    assert_eq!(mismatch(&err), ("pick", 0, "boolean", "int"));
+    assert_eq!(err.span().map(|span| (span.from, span.to)), Some((5, 5)));
+
+    let err = eval_err(&registry, "pick(true, 'a', [1])");
+    assert_eq!(mismatch(&err), ("pick", 2, "string", "list"));
+    assert_eq!(err.span().map(|span| (span.from, span.to)), Some((16, 16)));
+}
+
+fn return_type(registry: &Registry, src: &str) -> Type {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    Program::check(registry, &expr).unwrap_or_else(|errors| panic!("{src}: {errors:?}"))
+}
+
+#[test]
+fn functions_return_plain_rust_types() {
+    let mut registry = Registry::default();
+    registry
+        .add_fn(b"root", root)
+        .add_fn(b"abs", |v: i64| v.abs())
+        .add_fn(b"odd", |v: i64| v % 2 != 0)
+        .add_fn(b"greet", |name: String| format!("hi {name}"))
+        .add_fn(b"range", |n: i64| (0..n).collect::<Vec<i64>>())
+        .add_fn(b"safe_root", |v: f64| (v >= 0.0).then(|| v.sqrt()));
+
+    assert_eq!(eval_ok(&registry, "abs(-3)"), Value::Int(3));
+    assert_eq!(eval_ok(&registry, "odd(3)"), Value::Boolean(true));
+    assert_eq!(eval_ok(&registry, "greet('you')"), Value::from("hi you"));
+    assert_eq!(
+        eval_ok(&registry, "range(3)"),
+        Value::from(vec![0i64, 1, 2])
+    );
+    assert_eq!(eval_ok(&registry, "safe_root(9)"), Value::Float(3.0));
+    assert_eq!(eval_ok(&registry, "safe_root(-9)"), Value::Null);
+    assert_eq!(eval_ok(&registry, "safe_root(-9) ?? 0"), Value::Int(0));
+
+    assert_eq!(return_type(&registry, "abs(-3)"), Type::INT);
+    assert_eq!(return_type(&registry, "odd(3)"), Type::BOOLEAN);
+    assert_eq!(return_type(&registry, "greet('you')"), Type::STRING);
+    assert_eq!(return_type(&registry, "range(3)"), Type::LIST);
+    assert_eq!(return_type(&registry, "root(9)"), Type::FLOAT);
+    assert_eq!(
+        return_type(&registry, "safe_root(9)"),
+        Type::FLOAT | Type::NULL
+    );
+}
+
+#[test]
+fn return_types_follow_nested_rust_types() {
+    let mut registry = Registry::default();
+    registry
+        .add_fn(b"counts", |s: String| {
+            let mut counts = BTreeMap::new();
+            for c in s.chars() {
+                *counts.entry(c.to_string()).or_insert(0i64) += 1;
+            }
+            counts
+        })
+        .add_fn(b"index", |s: String| HashMap::from([(s, true)]))
+        .add_fn(b"halves", |n: i64| {
+            (0..n)
+                .map(|i| (i % 2 == 0).then_some(i / 2))
+                .collect::<Vec<_>>()
+        })
+        .add_fn(b"maybe_root", |v: f64| {
+            root(v).map(|v| (v > 1.0).then_some(v))
+        });
+
+    assert_eq!(
+        eval_ok(&registry, "counts('aba')"),
+        [("a", 2i64), ("b", 1)].into_iter().collect()
+    );
+    assert_eq!(
+        eval_ok(&registry, "index('a')"),
+        [("a", true)].into_iter().collect()
+    );
+    assert_eq!(
+        eval_ok(&registry, "halves(3)"),
+        Value::from(vec![Some(0i64), None, Some(1)])
+    );
+    assert_eq!(eval_ok(&registry, "maybe_root(4)"), Value::Float(2.0));
+    assert_eq!(eval_ok(&registry, "maybe_root(1)"), Value::Null);
+
+    assert_eq!(return_type(&registry, "counts('aba')"), Type::MAP);
+    assert_eq!(return_type(&registry, "index('a')"), Type::MAP);
+    assert_eq!(return_type(&registry, "halves(3)"), Type::LIST);
+    assert_eq!(
+        return_type(&registry, "maybe_root(4)"),
+        Type::FLOAT | Type::NULL
+    );
+}
@@ -0,0 +1,125 @@
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+use expr::{eval, eval_with_registry, Error, Registry, RuntimeError, RuntimeErrorKind, Value};
+
+fn eval_ok(src: &str) -> Value {
+    eval(src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+fn eval_err(src: &str) -> RuntimeError {
+    match eval(src) {
+        Err(Error::RuntimeError(err)) => err,
+        ret => panic!("{src}: expected a runtime error but got {ret:?}"),
+    }
+}
+
+fn ints(items: &[i64]) -> Value {
+    Value::List(items.iter().copied().map(Value::Int).collect())
+}
+
+#[test]
+fn higher_order_functions() {
+    assert_eq!(eval_ok("map([1, 2, 3], |v| v * 10)"), ints(&[10, 20, 30]));
+    assert_eq!(
+        eval_ok("filter([1, 2, 3, 4], |v| v % 2 == 0)"),
+        ints(&[2, 4])
+    );
+    assert_eq!(
+        eval_ok("reduce([1, 2, 3, 4], 0, |acc, v| acc + v)"),
+        Value::Int(10)
+    );
+    assert_eq!(eval_ok("any([1, 2, 3], |v| v > 2)"), Value::Boolean(true));
+    assert_eq!(eval_ok("any([1, 2, 3], |v| v > 3)"), Value::Boolean(false));
+    assert_eq!(eval_ok("all([1, 2, 3], |v| v > 0)"), Value::Boolean(true));
+    assert_eq!(eval_ok("all([1, 2, 3], |v| v > 1)"), Value::Boolean(false));
+}
+
+#[test]
+fn empty_and_null_lists() {
+    assert_eq!(eval_ok("map([], |v| v)"), ints(&[]));
+    assert_eq!(eval_ok("reduce([], 5, |acc, v| acc + v)"), Value::Int(5));
+    assert_eq!(eval_ok("any([], |v| true)"), Value::Boolean(false));
+    assert_eq!(eval_ok("all([], |v| false)"), Value::Boolean(true));
+    assert_eq!(eval_ok("map(null, |v| v)"), Value::Null);
+    assert_eq!(eval_ok("filter(null, |v| v)"), Value::Null);
+}
+
+#[test]
+fn lambdas_capture_outer_locals() {
+    assert_eq!(
+        eval_ok("let k = 3 in map([1, 2], |v| v * k)"),
+        ints(&[3, 6])
+    );
+    assert_eq!(
+        eval_ok("fn scale(xs, k) = map(xs, |v| v * k); scale([1, 2], 5)"),
+        ints(&[5, 10])
+    );
+    assert_eq!(
+        eval_ok("let v = 100 in map([1, 2], |v| v) == [1, 2] && v == 100"),
+        Value::Boolean(true)
+    );
+}
+
+#[test]
+fn nested_lambdas() {
+    assert_eq!(
+        eval_ok("map([[1, 2], [3]], |xs| reduce(xs, 0, |acc, v| acc + v))"),
+        ints(&[3, 3])
+    );
+    assert_eq!(
+        eval_ok("map([1, 2], |a| map([10, 20], |b| a + b))"),
+        Value::List(vec![ints(&[11, 21]), ints(&[12, 22])].into())
+    );
+    assert_eq!(
+        eval_ok("filter([1, 2, 3], |a| any([2, 3], |b| a == b))"),
+        ints(&[2, 3])
+    );
+}
+
+#[test]
+fn any_and_all_stop_at_the_first_decisive_element() {
+    let calls = Arc::new(AtomicI64::new(0));
+    let mut registry = Registry::default();
+    registry.add_shared_fn(b"visit", {
+        let calls = Arc::clone(&calls);
+        move |v: i64| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            v
+        }
+    });
+
+    let ret = eval_with_registry(&registry, "any([1, 2, 3, 4], |v| visit(v) == 2)").unwrap();
+    assert_eq!(ret, Value::Boolean(true));
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    let ret = eval_with_registry(&registry, "all([1, 2, 3, 4], |v| visit(v) < 3)").unwrap();
+    assert_eq!(ret, Value::Boolean(false));
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+}
+
+#[test]
+fn misused_lambdas_are_rejected() {
+    let err = eval_err("|v| v");
+    assert!(matches!(err.kind(), RuntimeErrorKind::MisplacedLambda));
+
+    let err = eval_err("map([1], 2)");
+    assert!(matches!(err.kind(), RuntimeErrorKind::ExpectedLambda(f, 1) if f == "map"));
+
+    let err = eval_err("reduce([1], 0, |v| v)");
+    assert!(matches!(err.kind(), RuntimeErrorKind::ExpectedLambda(f, 2) if f == "reduce"));
+
+    let err = eval_err("map([1])");
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::WrongArgumentCount(2, 1)
+    ));
+
+    let err = eval_err("map(5, |v| v)");
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::InvalidArgument("list", "int")
+    ));
+}
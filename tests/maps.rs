@@ -0,0 +1,91 @@
+use expr::{eval_with_registry, Error, Registry, RuntimeError, RuntimeErrorKind, Value};
+
+/// A registry with the maps `user`, `order` and `m`.
+fn registry() -> Registry {
+    let user = [("name", Value::from("ann")), ("age", Value::Int(30))];
+    let item = |price: f64| [("price", price)].into_iter().collect::<Value>();
+    let order = [("items", Value::List(vec![item(2.5), item(4.0)].into()))];
+
+    let mut registry = Registry::default();
+    registry
+        .add_var(b"user", user.into_iter().collect::<Value>())
+        .add_var(b"order", order.into_iter().collect::<Value>())
+        .add_var(
+            b"m",
+            [("key", 1), ("other key", 2)]
+                .into_iter()
+                .collect::<Value>(),
+        );
+    registry
+}
+
+fn eval_ok(src: &str) -> Value {
+    eval_with_registry(&registry(), src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+fn eval_err(src: &str) -> RuntimeError {
+    match eval_with_registry(&registry(), src) {
+        Err(Error::RuntimeError(err)) => err,
+        ret => panic!("{src}: expected a runtime error but got {ret:?}"),
+    }
+}
+
+#[test]
+fn members_read_map_values() {
+    for (src, expected) in [
+        ("user.age > 18", Value::Boolean(true)),
+        ("user.name + '!'", Value::from("ann!")),
+        ("order.items[0].price", Value::Float(2.5)),
+        ("order.items[-1].price * 2", Value::Float(8.0)),
+        ("sum(map(order.items, |i| i.price))", Value::Float(6.5)),
+    ] {
+        assert_eq!(eval_ok(src), expected, "{src}");
+    }
+}
+
+#[test]
+fn maps_are_indexed_by_string_keys() {
+    assert_eq!(eval_ok("m['key']"), Value::Int(1));
+    assert_eq!(eval_ok("m['other ' + 'key']"), Value::Int(2));
+    assert_eq!(eval_ok("m['key'] == m.key"), Value::Boolean(true));
+    assert_eq!(eval_ok("user['age']"), Value::Int(30));
+
+    let err = eval_err("m[1]");
+    assert!(
+        matches!(err.kind(), RuntimeErrorKind::InvalidIndex("map", "int")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn missing_keys_are_errors() {
+    let err = eval_err("user.missing");
+    assert!(
+        matches!(err.kind(), RuntimeErrorKind::MissingKey(key) if key == "missing"),
+        "{err:?}"
+    );
+    assert_eq!(err.span().map(|span| span.from), Some(4));
+
+    let err = eval_err("m['nope']");
+    assert!(
+        matches!(err.kind(), RuntimeErrorKind::MissingKey(key) if key == "nope"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn members_of_other_values_are_errors() {
+    for (src, target) in [
+        ("user.age.x", "int"),
+        ("user.name.length", "string"),
+        ("order.items.price", "list"),
+    ] {
+        let err = eval_err(src);
+        assert!(
+            matches!(err.kind(), RuntimeErrorKind::InvalidIndex(t, "string") if *t == target),
+            "{src}: {err:?}"
+        );
+    }
+
+    assert_eq!(eval_err("user.age.x").span().map(|span| span.from), Some(8));
+}
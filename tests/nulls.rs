@@ -0,0 +1,136 @@
+use expr::{eval, Context, Expr, Program, Registry, RuntimeErrorKind, Type, Value};
+
+fn eval_ok(src: &str) -> Value {
+    eval(src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+/// A strict registry with the optional variable `nick` and the plain `n`,
+/// which is null at runtime so nothing about it can be folded.
+fn setup() -> (Registry, Context) {
+    let mut registry = Registry::default();
+    registry.set_strict_types(true);
+    let nick = registry
+        .declare_optional_var(b"nick", Type::STRING)
+        .unwrap();
+    let n = registry.declare_var(b"n", Type::ANY).unwrap();
+
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(n, Value::Null).unwrap();
+    assert_eq!(registry.var_handle(b"nick"), Some(nick));
+    (registry, ctx)
+}
+
+fn run(registry: &Registry, ctx: &Context, src: &str) -> Value {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    let program = Program::compile(registry, &expr).unwrap_or_else(|err| panic!("{src}: {err:?}"));
+    program
+        .run(registry, ctx)
+        .unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+#[test]
+fn null_coalescing_picks_the_first_value_that_is_not_null() {
+    for (src, expected) in [
+        ("null ?? 1", Value::Int(1)),
+        ("2 ?? 1", Value::Int(2)),
+        ("null ?? null", Value::Null),
+        ("null ?? null ?? 'c'", Value::from("c")),
+        ("false ?? 1", Value::Boolean(false)),
+        ("0 ?? 1", Value::Int(0)),
+        ("[] ?? 1", Value::List(Vec::new().into())),
+    ] {
+        assert_eq!(eval_ok(src), expected, "{src}");
+    }
+}
+
+#[test]
+fn is_null_only_holds_for_null() {
+    assert_eq!(eval_ok("is_null(null)"), Value::Boolean(true));
+    for src in ["0", "0.0", "false", "''", "[]", "[null]"] {
+        assert_eq!(
+            eval_ok(&format!("is_null({src})")),
+            Value::Boolean(false),
+            "{src}"
+        );
+    }
+}
+
+#[test]
+fn optional_variables_are_null_until_set() {
+    let (registry, mut ctx) = setup();
+    let nick = registry.var_handle(b"nick").unwrap();
+
+    let expr = Expr::from_src(b"nick").unwrap();
+    assert_eq!(
+        Program::check(&registry, &expr).unwrap(),
+        Type::STRING | Type::NULL
+    );
+    assert_eq!(run(&registry, &ctx, "nick"), Value::Null);
+    assert_eq!(run(&registry, &ctx, "is_null(nick)"), Value::Boolean(true));
+    assert_eq!(run(&registry, &ctx, "nick ?? 'anon'"), Value::from("anon"));
+
+    ctx.set_var(nick, "ann").unwrap();
+    assert_eq!(run(&registry, &ctx, "nick ?? 'anon'"), Value::from("ann"));
+    assert_eq!(run(&registry, &ctx, "is_null(nick)"), Value::Boolean(false));
+
+    ctx.set_var(nick, Value::Null).unwrap();
+    assert_eq!(run(&registry, &ctx, "nick"), Value::Null);
+}
+
+#[test]
+fn other_undeclared_variables_still_fail() {
+    let (registry, _) = setup();
+    let expr = Expr::from_src(b"nik ?? 'anon'").unwrap();
+    let err = Program::compile(&registry, &expr).unwrap_err();
+    assert!(
+        matches!(err.kind(), RuntimeErrorKind::TypeCheck(_)),
+        "{err:?}"
+    );
+
+    let mut registry = Registry::default();
+    registry
+        .declare_optional_var(b"nick", Type::STRING)
+        .unwrap();
+    let err = Program::compile(&registry, &expr).unwrap_err();
+    assert!(
+        matches!(err.kind(), RuntimeErrorKind::UndeclaredVariable(name) if name == "nik"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn null_passes_through_every_operator() {
+    let (registry, ctx) = setup();
+    for op in [
+        "+", "-", "*", "/", "%", "**", "&", "|", "^", "<<", ">>", ">>>",
+    ] {
+        for src in [
+            format!("n {op} 1"),
+            format!("1 {op} n"),
+            format!("n {op} n"),
+            format!("null {op} 1"),
+            format!("1 {op} null"),
+        ] {
+            assert_eq!(run(&registry, &ctx, &src), Value::Null, "{src}");
+        }
+    }
+
+    for (src, expected) in [
+        ("-n", Value::Null),
+        ("-null", Value::Null),
+        ("~n", Value::Null),
+        ("~null", Value::Null),
+        ("!n", Value::Boolean(true)),
+        ("!null", Value::Boolean(true)),
+        ("n == null", Value::Boolean(true)),
+        ("n != null", Value::Boolean(false)),
+        ("n == 0", Value::Boolean(false)),
+        ("n < 1", Value::Boolean(false)),
+        ("n >= 1", Value::Boolean(false)),
+        ("null <= null", Value::Boolean(true)),
+        ("n && true", Value::Boolean(false)),
+        ("n || true", Value::Boolean(true)),
+    ] {
+        assert_eq!(run(&registry, &ctx, src), expected, "{src}");
+    }
+}
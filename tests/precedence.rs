@@ -0,0 +1,119 @@
+use expr::Expr;
+
+/// Binary operators from loosest to tightest binding, operators on the same
+/// row share a precedence level.
+const LEVELS: &[&[&str]] = &[
+    &["??"],
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>", ">>>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+fn parse(src: &str) -> String {
+    match Expr::from_src(src.as_bytes()) {
+        Ok(expr) => expr.to_string(),
+        Err(err) => panic!("failed to parse `{src}`: {err}"),
+    }
+}
+
+fn level(op: &str) -> usize {
+    LEVELS.iter().position(|ops| ops.contains(&op)).unwrap()
+}
+
+#[test]
+fn every_operator_pair() {
+    let ops: Vec<&str> = LEVELS.iter().flat_map(|ops| ops.iter().copied()).collect();
+    for lhs in &ops {
+        for rhs in &ops {
+            let expected = if level(lhs) >= level(rhs) {
+                format!("((a {lhs} b) {rhs} c)")
+            } else {
+                format!("(a {lhs} (b {rhs} c))")
+            };
+
+            assert_eq!(parse(&format!("a {lhs} b {rhs} c")), expected);
+        }
+    }
+}
+
+#[test]
+fn left_associative() {
+    for op in LEVELS.iter().flat_map(|ops| ops.iter()) {
+        assert_eq!(
+            parse(&format!("a {op} b {op} c {op} d")),
+            format!("(((a {op} b) {op} c) {op} d)")
+        );
+    }
+}
+
+#[test]
+fn unary_binds_tighter_than_binary() {
+    for op in LEVELS.iter().flat_map(|ops| ops.iter()) {
+        assert_eq!(parse(&format!("-a {op} b")), format!("((-a) {op} b)"));
+        assert_eq!(parse(&format!("!a {op} b")), format!("((!a) {op} b)"));
+        assert_eq!(parse(&format!("~a {op} b")), format!("((~a) {op} b)"));
+        assert_eq!(parse(&format!("a {op} -b")), format!("(a {op} (-b))"));
+    }
+
+    assert_eq!(parse("-f(a)"), "(-f(a))");
+    assert_eq!(parse("-a[0][1]"), "(-a[0][1])");
+    assert_eq!(parse("!-a"), "(!(-a))");
+    assert_eq!(parse("!~a"), "(!(~a))");
+}
+
+#[test]
+fn power_is_right_associative_and_binds_tighter_than_unary() {
+    for op in LEVELS.iter().flat_map(|ops| ops.iter()) {
+        assert_eq!(
+            parse(&format!("a ** b {op} c")),
+            format!("((a ** b) {op} c)")
+        );
+        assert_eq!(
+            parse(&format!("a {op} b ** c")),
+            format!("(a {op} (b ** c))")
+        );
+    }
+
+    assert_eq!(parse("a ** b ** c"), "(a ** (b ** c))");
+    assert_eq!(parse("-a ** b"), "(-(a ** b))");
+    assert_eq!(parse("!a ** b"), "(!(a ** b))");
+    assert_eq!(parse("~a ** b"), "(~(a ** b))");
+    assert_eq!(parse("a ** -b ** c"), "(a ** (-(b ** c)))");
+    assert_eq!(parse("a[0] ** f(b)"), "(a[0] ** f(b))");
+}
+
+#[test]
+fn parentheses_override_precedence() {
+    assert_eq!(parse("(a + b) * c"), "((a + b) * c)");
+    assert_eq!(parse("a - (b - c)"), "(a - (b - c))");
+    assert_eq!(parse("f((a || b) && c, d)"), "f(((a || b) && c), d)");
+}
+
+#[test]
+fn mixed_logical_expression() {
+    assert_eq!(
+        parse("a == 1 && b != 2 || c < 3"),
+        "(((a == 1) && (b != 2)) || (c < 3))"
+    );
+}
+
+#[test]
+fn conditional_is_loosest_and_right_associative() {
+    for op in LEVELS.iter().flat_map(|ops| ops.iter()) {
+        assert_eq!(
+            parse(&format!("a {op} b ? c {op} d : e {op} f")),
+            format!("((a {op} b) ? (c {op} d) : (e {op} f))")
+        );
+    }
+
+    assert_eq!(parse("a ? b : c ? d : e"), "(a ? b : (c ? d : e))");
+    assert_eq!(parse("a ? b ? c : d : e"), "(a ? (b ? c : d) : e)");
+    assert_eq!(parse("if(a, b, c) + 1"), "((a ? b : c) + 1)");
+}
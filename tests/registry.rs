@@ -0,0 +1,185 @@
+use expr::{
+    Context, Expr, Program, Registry, RegistryError, RuntimeError, RuntimeErrorKind, Type,
+    TypeErrorKind, Value,
+};
+
+const BUILTINS: [&str; 23] = [
+    "pow", "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "asinh", "acosh",
+    "atanh", "exp", "ln", "log10", "log2", "sqrt", "cbrt", "max", "min", "sum", "len",
+];
+
+#[test]
+fn default_registry_lists_its_builtins() {
+    let registry = Registry::default();
+    let names: Vec<_> = registry
+        .fns()
+        .map(|(name, _)| String::from_utf8_lossy(name).into_owned())
+        .collect();
+    assert_eq!(names[..BUILTINS.len()], BUILTINS);
+    assert_eq!(names[BUILTINS.len()..], ["is_null"]);
+
+    // Every builtin is documented and pure
+    for (name, signature) in registry.fns() {
+        let name = String::from_utf8_lossy(name);
+        assert!(signature.doc().is_some_and(|doc| !doc.is_empty()), "{name}");
+        assert!(signature.is_pure(), "{name}");
+    }
+
+    let vars: Vec<_> = registry.vars().collect();
+    assert_eq!(vars.len(), 1);
+    assert_eq!(vars[0].0, b"PI");
+    assert_eq!(vars[0].1.ty(), Type::FLOAT);
+    assert_eq!(vars[0].1.value(), Some(&Value::Float(std::f64::consts::PI)));
+}
+
+#[test]
+fn signatures_describe_the_parameters() {
+    let registry = Registry::default();
+
+    let pow = registry.fn_signature(b"pow").unwrap();
+    let params: Vec<_> = pow.params().iter().map(|p| (p.name(), p.ty())).collect();
+    let number = Type::NUMBER | Type::NULL;
+    assert_eq!(params, [(Some("base"), number), (Some("exp"), number)]);
+    assert_eq!(pow.ret(), number);
+    assert_eq!((pow.min_args(), pow.max_args()), (2, Some(2)));
+    assert_eq!(pow.doc(), Some("Raise `base` to the power of `exp`"));
+
+    let sin = registry.fn_signature(b"sin").unwrap();
+    assert_eq!(sin.params()[0].name(), Some("x"));
+    assert_eq!(sin.ret(), Type::FLOAT | Type::NULL);
+    assert_eq!(sin.doc(), Some("Sine of `x` radians"));
+
+    let max = registry.fn_signature(b"max").unwrap();
+    assert!(max.params().is_empty());
+    assert_eq!((max.min_args(), max.max_args()), (1, None));
+    assert!(max.accepts(5) && !max.accepts(0));
+
+    assert!(registry.fn_signature(b"nope").is_none());
+    assert!(Registry::empty().fn_signature(b"pow").is_none());
+}
+
+#[test]
+fn pow_only_takes_numbers() {
+    let registry = Registry::default();
+    let expr = Expr::from_src(b"pow('2', 3)").unwrap();
+    let errors = Program::check(&registry, &expr).unwrap_err();
+    assert!(
+        matches!(
+            errors[0].kind(),
+            TypeErrorKind::ArgumentTypeMismatch { function, index: 0, got, .. }
+                if function == "pow" && *got == Type::STRING
+        ),
+        "{errors:?}"
+    );
+
+    let expr = Expr::from_src(b"pow(2, null)").unwrap();
+    assert_eq!(
+        Program::check(&registry, &expr).unwrap(),
+        Type::NUMBER | Type::NULL
+    );
+}
+
+#[test]
+fn types_print_their_kinds() {
+    assert_eq!(format!("{:?}", Type::INT), "Type(int)");
+    assert_eq!(
+        format!("{:?}", Type::FLOAT | Type::NULL),
+        "Type(float | null)"
+    );
+    assert_eq!(format!("{:?}", Type::ANY), "Type(any)");
+    assert_eq!((Type::STRING | Type::LIST).to_string(), "string | list");
+}
+
+fn compile(registry: &Registry, src: &str) -> Program {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    Program::compile(registry, &expr).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+fn run(registry: &Registry, program: &Program) -> Result<Value, RuntimeError> {
+    program.run(registry, &Context::new(registry))
+}
+
+#[test]
+fn replaced_functions_keep_their_slot() {
+    let mut registry = Registry::empty();
+    registry.add_fn(b"f", |v: i64| v + 1);
+    let program = compile(&registry, "f(1)");
+    assert_eq!(run(&registry, &program).unwrap(), Value::Int(2));
+
+    registry.add_fn(b"f", |v: i64| v * 10);
+    assert_eq!(registry.fns().count(), 1);
+    assert_eq!(run(&registry, &program).unwrap(), Value::Int(10));
+
+    // Programs checked against the old arity fail instead of calling it
+    registry.add_fn(b"f", |a: i64, b: i64| a + b);
+    assert!(matches!(
+        run(&registry, &program).unwrap_err().kind(),
+        RuntimeErrorKind::WrongArgumentCount(2, 1)
+    ));
+    assert_eq!(registry.fns().count(), 1);
+}
+
+#[test]
+fn removed_functions_are_undeclared_until_registered_again() {
+    let mut registry = Registry::empty();
+    registry.add_fn(b"f", |v: i64| v + 1).add_fn(b"g", || 0i64);
+    let program = compile(&registry, "f(1)");
+
+    assert!(registry.remove_fn(b"f"));
+    assert!(!registry.remove_fn(b"f"));
+    assert!(!registry.remove_fn(b"nope"));
+    assert!(registry.fn_signature(b"f").is_none());
+    let names: Vec<_> = registry.fns().map(|(name, _)| name).collect();
+    assert_eq!(names, [b"g"]);
+
+    assert!(matches!(
+        run(&registry, &program).unwrap_err().kind(),
+        RuntimeErrorKind::UndeclaredFunction(name) if name == "f"
+    ));
+    let expr = Expr::from_src(b"f(1)").unwrap();
+    assert!(matches!(
+        Program::compile(&registry, &expr).unwrap_err().kind(),
+        RuntimeErrorKind::UndeclaredFunction(name) if name == "f"
+    ));
+
+    registry.add_fn(b"f", |v: i64| v - 1);
+    assert_eq!(run(&registry, &program).unwrap(), Value::Int(0));
+}
+
+#[test]
+fn try_add_refuses_duplicates() {
+    let mut registry = Registry::empty();
+    registry
+        .try_add_var(b"a", 1)
+        .unwrap()
+        .try_add_var(b"b", 2)
+        .unwrap();
+    assert!(matches!(
+        registry.try_add_var(b"a", 3),
+        Err(RegistryError::DuplicateVariable(name)) if name == "a"
+    ));
+    assert_eq!(
+        registry.get_var(b"a").unwrap().value(),
+        Some(&Value::Int(1))
+    );
+
+    registry.try_add_fn(b"f", || 1i64).unwrap();
+    assert!(matches!(
+        registry.try_add_fn(b"f", || 2i64),
+        Err(RegistryError::DuplicateFunction(name)) if name == "f"
+    ));
+    let program = compile(&registry, "f() + a");
+    assert_eq!(run(&registry, &program).unwrap(), Value::Int(2));
+
+    // Variables and functions have their own names, a removed one is free again
+    registry.try_add_fn(b"a", || 3i64).unwrap();
+    registry.try_add_var(b"f", 4).unwrap();
+    registry.remove_var(b"a");
+    registry.remove_fn(b"f");
+    registry.try_add_var(b"a", 5).unwrap();
+    registry.try_add_fn(b"f", || 6i64).unwrap();
+    assert_eq!(run(&registry, &program).unwrap(), Value::Int(11));
+
+    let err = RegistryError::DuplicateFunction("f".into());
+    assert_eq!(err.to_string(), "Function f is already registered");
+}
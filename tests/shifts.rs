@@ -0,0 +1,103 @@
+use expr::{
+    eval_with_context, Context, Error, Expr, Program, Registry, RuntimeErrorKind, Type, Value,
+};
+
+/// Evaluate `{a} op {b}` once with literals, which the compiler folds, and
+/// once with variables, which are only known when running.
+fn eval_both_ways(a: i64, op: &str, b: i64) -> [Result<Value, RuntimeErrorKind>; 2] {
+    let mut registry = Registry::default();
+    let va = registry.declare_var(b"a", Type::INT).unwrap();
+    let vb = registry.declare_var(b"b", Type::INT).unwrap();
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(va, a).unwrap().set_var(vb, b).unwrap();
+
+    [format!("({a}) {op} ({b})"), format!("a {op} b")].map(|src| {
+        eval_with_context(&registry, &ctx, &src).map_err(|err| match err {
+            Error::RuntimeError(err) => match err.kind() {
+                RuntimeErrorKind::InvalidShift(amount) => RuntimeErrorKind::InvalidShift(*amount),
+                kind => panic!("{src}: {kind:?}"),
+            },
+            err => panic!("{src}: {err:?}"),
+        })
+    })
+}
+
+fn assert_both_ways(a: i64, op: &str, b: i64, expected: Value) {
+    for ret in eval_both_ways(a, op, b) {
+        assert_eq!(ret.unwrap(), expected, "{a} {op} {b}");
+    }
+}
+
+#[test]
+fn shifts_move_bits() {
+    assert_both_ways(1, "<<", 3, Value::Int(8));
+    assert_both_ways(1, "<<", 63, Value::Int(i64::MIN));
+    assert_both_ways(3, "<<", 63, Value::Int(i64::MIN));
+    assert_both_ways(40, ">>", 3, Value::Int(5));
+    assert_both_ways(40, ">>>", 3, Value::Int(5));
+    assert_both_ways(5, ">>", 0, Value::Int(5));
+    assert_both_ways(i64::MAX, ">>>", 63, Value::Int(0));
+}
+
+#[test]
+fn only_unsigned_shifts_drop_the_sign() {
+    // `>>` copies the sign bit in, `>>>` shifts zeroes in
+    assert_both_ways(-16, ">>", 2, Value::Int(-4));
+    assert_both_ways(-16, ">>>", 2, Value::Int((-16i64 as u64 >> 2) as i64));
+    assert_both_ways(-16, ">>>", 60, Value::Int(15));
+    assert_both_ways(-1, ">>", 1, Value::Int(-1));
+    assert_both_ways(-1, ">>", 63, Value::Int(-1));
+    assert_both_ways(-1, ">>>", 1, Value::Int(i64::MAX));
+    assert_both_ways(-1, ">>>", 63, Value::Int(1));
+    assert_both_ways(-7, ">>", 1, Value::Int(-4));
+}
+
+#[test]
+fn amounts_out_of_range_are_errors() {
+    for op in ["<<", ">>", ">>>"] {
+        for amount in [-1, -64, i64::MIN + 1, 64, 65, i64::MAX] {
+            let [folded, runtime] = eval_both_ways(1, op, amount);
+            assert!(
+                matches!(folded, Err(RuntimeErrorKind::InvalidShift(a)) if a == amount),
+                "1 {op} {amount}: {folded:?}"
+            );
+            assert!(
+                matches!(runtime, Err(RuntimeErrorKind::InvalidShift(a)) if a == amount),
+                "1 {op} {amount}: {runtime:?}"
+            );
+        }
+    }
+
+    let err = eval_both_ways(1, "<<", 64)[0]
+        .as_ref()
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "Shift amount 64 is out of range (expected: 0 to 63)");
+}
+
+fn compile(registry: &Registry, src: &str) -> Program {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    Program::compile(registry, &expr).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+#[test]
+fn folding_keeps_failing_shifts_for_the_runtime() {
+    let registry = Registry::default();
+    let ctx = Context::new(&registry);
+
+    let program = compile(&registry, "(1 << 4) + (-8 >> 1) + (-1 >>> 62)");
+    assert!(!format!("{program:?}").contains("BinaryOp"), "{program:?}");
+    assert_eq!(program.run(&registry, &ctx).unwrap(), Value::Int(15));
+
+    // The error is the one running it unfolded gives, with its span
+    let program = compile(&registry, "2 + (1 << 64)");
+    let err = program.run(&registry, &ctx).unwrap_err();
+    assert!(
+        matches!(err.kind(), RuntimeErrorKind::InvalidShift(64)),
+        "{err:?}"
+    );
+    assert_eq!(err.span().map(|span| span.from), Some(7));
+
+    let program = compile(&registry, "false ? 1 >> -1 : 3");
+    assert_eq!(program.run(&registry, &ctx).unwrap(), Value::Int(3));
+}
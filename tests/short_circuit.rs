@@ -0,0 +1,95 @@
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+use expr::{Context, Expr, Program, Registry, Type, Value};
+
+/// A registry with `tick(v)`, which counts its calls and returns `v`, and
+/// the variables `t = true`, `f = false` and `n = null`.
+fn setup() -> (Registry, Context, Arc<AtomicI64>) {
+    let calls = Arc::new(AtomicI64::new(0));
+    let mut registry = Registry::default();
+    registry.add_fn(b"tick", {
+        let calls = Arc::clone(&calls);
+        move |v: Value| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            v
+        }
+    });
+
+    let t = registry.declare_var(b"t", Type::BOOLEAN).unwrap();
+    let f = registry.declare_var(b"f", Type::BOOLEAN).unwrap();
+    let n = registry.declare_var(b"n", Type::ANY).unwrap();
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(t, true).unwrap();
+    ctx.set_var(f, false).unwrap();
+    ctx.set_var(n, Value::Null).unwrap();
+    (registry, ctx, calls)
+}
+
+fn compile(registry: &Registry, src: &str) -> Program {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    Program::compile(registry, &expr).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+/// Run every source, checking its value and how often `tick` was called.
+fn assert_runs(cases: &[(&str, Value, i64)]) {
+    let (registry, ctx, calls) = setup();
+    for (src, expected, ticks) in cases {
+        let ret = compile(&registry, src).run(&registry, &ctx).unwrap();
+        assert_eq!(&ret, expected, "{src}");
+        assert_eq!(calls.swap(0, Ordering::Relaxed), *ticks, "{src}");
+    }
+}
+
+#[test]
+fn skipped_operands_are_not_evaluated() {
+    assert_runs(&[
+        ("f && tick(true)", Value::Boolean(false), 0),
+        ("t && tick(true)", Value::Boolean(true), 1),
+        ("t || tick(false)", Value::Boolean(true), 0),
+        ("f || tick(false)", Value::Boolean(false), 1),
+        ("t ? tick(1) : tick(2)", Value::Int(1), 1),
+        ("f ? tick(1) : tick(2)", Value::Int(2), 1),
+        ("tick(3) ?? tick(4)", Value::Int(3), 1),
+        ("n ?? tick(4)", Value::Int(4), 1),
+        ("f && tick(t) || tick(f)", Value::Boolean(false), 1),
+        ("t ? (f && tick(t)) : tick(f)", Value::Boolean(false), 0),
+    ]);
+}
+
+#[test]
+fn constant_conditions_are_folded_away() {
+    let (registry, ctx, calls) = setup();
+    for (src, expected) in [
+        ("true || tick(false)", Value::Boolean(true)),
+        ("1 > 2 && tick(true)", Value::Boolean(false)),
+        ("false ? tick(1) : 1", Value::Int(1)),
+        ("(1 ?? tick(2)) + (true ? 3 : tick(4))", Value::Int(4)),
+    ] {
+        let program = compile(&registry, src);
+        assert!(
+            !format!("{program:?}").contains("Call"),
+            "{src}: {program:?}"
+        );
+        assert_eq!(program.run(&registry, &ctx).unwrap(), expected, "{src}");
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn folding_inside_branches_keeps_jumps_intact() {
+    assert_runs(&[
+        ("t && 1 + 1 == 2", Value::Boolean(true), 0),
+        ("(t ? 1 + 2 : 4 * 5) + 1", Value::Int(4), 0),
+        ("(f ? 1 + 2 : 4 * 5) + 1", Value::Int(21), 0),
+        ("(f || 2 > 1) ? tick(1) + 1 : tick(2) * 2", Value::Int(2), 1),
+        ("[t && true, f || true, n ?? 1 + 1]", list(), 0),
+        ("t ? (false ? tick(1) : 2) : tick(3)", Value::Int(2), 0),
+    ]);
+}
+
+fn list() -> Value {
+    Value::List(vec![Value::Boolean(true), Value::Boolean(true), Value::Int(2)].into())
+}
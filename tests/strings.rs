@@ -0,0 +1,126 @@
+use expr::{
+    eval, eval_with_context, eval_with_registry, Context, Error, Expr, ParseErrorKind, Registry,
+    RuntimeErrorKind, Type, Value,
+};
+
+fn eval_ok(src: &str) -> Value {
+    eval(src).unwrap_or_else(|err| panic!("{src}: {err:?}"))
+}
+
+#[test]
+fn escapes_are_unescaped() {
+    for (src, expected) in [
+        (r#""a\nb""#, "a\nb"),
+        (r#""\r\t\0""#, "\r\t\0"),
+        (r#""\\""#, "\\"),
+        (r#""say \"hi\"""#, "say \"hi\""),
+        (r#"'it\'s'"#, "it's"),
+        (r#"'"' + "'""#, "\"'"),
+        (r#""\u{e9}\u{1F600}""#, "é😀"),
+        (r#""\u{41}BC""#, "ABC"),
+    ] {
+        assert_eq!(eval_ok(src), Value::from(expected), "{src}");
+    }
+}
+
+#[test]
+fn invalid_escapes_are_rejected_at_the_backslash() {
+    for (src, escape, from) in [
+        (r#""\q""#, 'q', 1),
+        (r#""ab\x41""#, 'x', 3),
+        (r#""\u41""#, 'u', 1),
+        (r#""\u{zz}""#, 'u', 1),
+        (r#""\u{d800}""#, 'u', 1),
+        (r#""\u{41""#, 'u', 1),
+    ] {
+        let err = Expr::from_src(src.as_bytes()).unwrap_err();
+        assert!(
+            matches!(err.kind(), ParseErrorKind::InvalidEscape(c) if *c == escape),
+            "{src}: {err:?}"
+        );
+        assert_eq!(
+            err.span().map(|span| (span.from, span.to)),
+            Some((from, from + 1))
+        );
+    }
+}
+
+#[test]
+fn plus_concatenates_strings_and_lists() {
+    for (src, expected) in [
+        ("'ab' + 'cd'", Value::from("abcd")),
+        ("'' + ''", Value::from("")),
+        ("'n' + 1", Value::from("n1")),
+        ("1.5 + 'n'", Value::from("1.5n")),
+        ("'is ' + true", Value::from("is true")),
+        ("'a' + 'b' + 1 + 2", Value::from("ab12")),
+        ("1 + 2 + 'a'", Value::from("3a")),
+        ("[1] + [2, 3]", Value::from(vec![1i64, 2, 3])),
+    ] {
+        assert_eq!(eval_ok(src), expected, "{src}");
+    }
+}
+
+/// Evaluate `template` with `{a}` replaced by the literal `a`, which the
+/// compiler folds, and by a variable, which is only known when running.
+fn eval_both_ways(template: &str, a: &str) -> [Result<Value, String>; 2] {
+    let mut registry = Registry::default();
+    let var = registry.declare_var(b"a", Type::ANY).unwrap();
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(var, eval_ok(a)).unwrap();
+
+    let folded = template.replace("{a}", a);
+    let runtime = template.replace("{a}", "a");
+    [folded, runtime].map(|src| {
+        eval_with_context(&registry, &ctx, &src).map_err(|err| match err {
+            Error::RuntimeError(err) => err.to_string(),
+            err => panic!("{src}: {err:?}"),
+        })
+    })
+}
+
+#[test]
+fn other_operators_reject_strings_lists_and_maps() {
+    #[rustfmt::skip]
+    let cases = [
+        ("{a} * 2", "'a'", "Operator * cannot be applied to string and int"),
+        ("2 - {a}", "'3'", "Operator - cannot be applied to int and string"),
+        ("{a} & 1", "'5'", "Operator & cannot be applied to string and int"),
+        ("{a} << 1", "'1'", "Operator << cannot be applied to string and int"),
+        ("{a} ** 2", "'2'", "Operator ** cannot be applied to string and int"),
+        ("{a} / 2", "[4]", "Operator / cannot be applied to list and int"),
+        ("{a} + 1", "[1]", "Operator + cannot be applied to list and int"),
+        ("-{a}", "'3'", "Operator - cannot be applied to string"),
+        ("-{a}", "[1]", "Operator - cannot be applied to list"),
+        ("~{a}", "'1'", "Operator ~ cannot be applied to string"),
+    ];
+    for (template, a, expected) in cases {
+        let [folded, runtime] = eval_both_ways(template, a);
+        assert_eq!(folded, Err(expected.to_string()), "{template} with {a}");
+        assert_eq!(runtime, folded, "{template} with {a}");
+    }
+
+    // Maps have no literal, they only come from the host
+    let mut registry = Registry::default();
+    registry.add_var(b"m", [("k", 1)].into_iter().collect::<Value>());
+    for (src, expected) in [
+        ("m % 2", "Operator % cannot be applied to map and int"),
+        ("1 | m", "Operator | cannot be applied to int and map"),
+        ("-m", "Operator - cannot be applied to map"),
+    ] {
+        match eval_with_registry(&registry, src) {
+            Err(Error::RuntimeError(err)) => {
+                assert!(
+                    matches!(
+                        err.kind(),
+                        RuntimeErrorKind::InvalidOperands(..)
+                            | RuntimeErrorKind::InvalidOperand(..)
+                    ),
+                    "{src}: {err:?}"
+                );
+                assert_eq!(err.to_string(), expected, "{src}");
+            }
+            ret => panic!("{src}: expected a runtime error but got {ret:?}"),
+        }
+    }
+}
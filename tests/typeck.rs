@@ -0,0 +1,103 @@
+use expr::{
+    Context, Expr, Program, Registry, RuntimeErrorKind, Span, Type, TypeError, TypeErrorKind, Value,
+};
+
+fn check(registry: &Registry, src: &str) -> Result<Type, Vec<TypeError>> {
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    Program::check(registry, &expr)
+}
+
+fn at(span: Span) -> (usize, usize) {
+    (span.from, span.to)
+}
+
+#[test]
+fn every_error_is_reported_with_its_span() {
+    let errors = check(&Registry::default(), "true + sin(1 == 2)").unwrap_err();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+
+    // The comparison is the mismatched argument of `sin`
+    assert!(matches!(
+        errors[0].kind(),
+        TypeErrorKind::ArgumentTypeMismatch { function, index: 0, got, .. }
+            if function == "sin" && *got == Type::BOOLEAN
+    ));
+    assert_eq!(at(errors[0].span()), (13, 14));
+
+    assert!(matches!(
+        errors[1].kind(),
+        TypeErrorKind::InvalidOperands(_, a, _) if *a == Type::BOOLEAN
+    ));
+    assert_eq!(at(errors[1].span()), (5, 5));
+}
+
+#[test]
+fn wrong_argument_counts_are_reported() {
+    let errors = check(&Registry::default(), "pow(1)").unwrap_err();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(matches!(
+        errors[0].kind(),
+        TypeErrorKind::WrongArgumentCount(2, 1)
+    ));
+    assert_eq!(at(errors[0].span()), (0, 2));
+}
+
+#[test]
+fn independent_mistakes_are_all_collected() {
+    let errors = check(&Registry::default(), "-'a' + len(1, 2) + nope").unwrap_err();
+    let kinds: Vec<_> = errors.iter().map(TypeError::kind).collect();
+    assert!(matches!(
+        kinds[..],
+        [
+            TypeErrorKind::InvalidOperand(..),
+            TypeErrorKind::WrongArgumentCount(1, 2),
+            TypeErrorKind::UndeclaredVariable(_),
+        ]
+    ));
+}
+
+#[test]
+fn types_come_from_variables_and_signatures() {
+    let mut registry = Registry::default();
+    registry
+        .add_var(b"name", "expr")
+        .declare_var(b"n", Type::INT)
+        .unwrap();
+    registry.add_fn(b"half", |v: f64| v / 2.0);
+
+    assert_eq!(check(&registry, "name").unwrap(), Type::STRING);
+    assert_eq!(check(&registry, "n * 2").unwrap(), Type::INT);
+    assert_eq!(check(&registry, "half(n)").unwrap(), Type::FLOAT);
+
+    let errors = check(&registry, "half(name)").unwrap_err();
+    assert!(matches!(
+        errors[0].kind(),
+        TypeErrorKind::ArgumentTypeMismatch { expected, got, .. }
+            if expected.contains(Type::FLOAT) && *got == Type::STRING
+    ));
+    assert_eq!(at(errors[0].span()), (5, 8));
+}
+
+#[test]
+fn strict_types_reject_at_compile_time() {
+    let mut registry = Registry::default();
+    let expr = Expr::from_src(b"true + sin(1 == 2)").unwrap();
+
+    // Without strict types the mistake only shows when running
+    assert!(Program::compile(&registry, &expr).is_ok());
+
+    registry.set_strict_types(true);
+    let err = Program::compile(&registry, &expr).unwrap_err();
+    match err.kind() {
+        RuntimeErrorKind::TypeCheck(errors) => assert_eq!(errors.len(), 2),
+        kind => panic!("expected a type check error but got {kind:?}"),
+    }
+    assert_eq!(err.span().map(at), Some((13, 14)));
+
+    let expr = Expr::from_src(b"pow(2, 3) + 1").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    assert_eq!(
+        program.run(&registry, &Context::new(&registry)).unwrap(),
+        Value::Int(9)
+    );
+}
@@ -0,0 +1,137 @@
+use expr::{Context, Expr, Program, Registry, RuntimeErrorKind, Type, Value};
+
+/// One value of every variant with its truthiness.
+fn values() -> Vec<(Value, bool)> {
+    let map = |len: usize| (0..len).map(|i| (i.to_string(), i as i64)).collect();
+    vec![
+        (Value::Int(0), false),
+        (Value::Int(-3), true),
+        (Value::Float(0.0), false),
+        (Value::Float(-0.0), false),
+        (Value::Float(f64::NAN), false),
+        (Value::Float(0.5), true),
+        (Value::Boolean(false), false),
+        (Value::Boolean(true), true),
+        (Value::from(""), false),
+        (Value::from("0"), true),
+        (Value::List(Vec::new().into()), false),
+        (Value::from(vec![Value::Null]), true),
+        (map(0), false),
+        (map(2), true),
+        (Value::Null, false),
+    ]
+}
+
+/// Compile `src` against a registry where `x` may be anything.
+fn compile(src: &str) -> (Registry, Program) {
+    let mut registry = Registry::default();
+    registry.declare_var(b"x", Type::ANY).unwrap();
+    let expr = Expr::from_src(src.as_bytes()).unwrap();
+    let program = Program::compile(&registry, &expr).unwrap_or_else(|err| panic!("{src}: {err:?}"));
+    (registry, program)
+}
+
+fn run_with(registry: &Registry, program: &Program, x: Value) -> Result<Value, RuntimeErrorKind> {
+    let mut ctx = Context::new(registry);
+    ctx.set_var(registry.var_handle(b"x").unwrap(), x).unwrap();
+    program.run(registry, &ctx).map_err(|err| match err.kind() {
+        RuntimeErrorKind::InvalidOperand(op, got) => RuntimeErrorKind::InvalidOperand(*op, got),
+        kind => panic!("{kind:?}"),
+    })
+}
+
+#[test]
+fn not_gives_a_boolean_for_every_value() {
+    let (registry, not) = compile("!x");
+    let (_, not_not) = compile("!!x");
+    for (x, truthy) in values() {
+        let ret = run_with(&registry, &not, x.clone()).unwrap();
+        assert_eq!(ret, Value::Boolean(!truthy), "!{x:?}");
+        let ret = run_with(&registry, &not_not, x.clone()).unwrap();
+        assert_eq!(ret, Value::Boolean(truthy), "!!{x:?}");
+    }
+}
+
+#[test]
+fn bit_not_complements_ints() {
+    let (registry, program) = compile("~x");
+    for (x, expected) in [
+        (Value::Int(0), Value::Int(-1)),
+        (Value::Int(5), Value::Int(-6)),
+        (Value::Int(-1), Value::Int(0)),
+        (Value::Int(i64::MIN), Value::Int(i64::MAX)),
+        (Value::Boolean(true), Value::Int(-2)),
+        (Value::Null, Value::Null),
+    ] {
+        assert_eq!(
+            run_with(&registry, &program, x.clone()).unwrap(),
+            expected,
+            "~{x:?}"
+        );
+    }
+
+    let (_, twice) = compile("~~x");
+    assert_eq!(
+        run_with(&registry, &twice, Value::Int(42)).unwrap(),
+        Value::Int(42)
+    );
+}
+
+#[test]
+fn bit_not_rejects_everything_but_ints() {
+    let (registry, program) = compile("~x");
+    for (x, got) in [
+        (Value::Float(1.0), "float"),
+        (Value::from("1"), "string"),
+        (Value::from(vec![1i64]), "list"),
+        ([("a", 1)].into_iter().collect(), "map"),
+    ] {
+        let err = run_with(&registry, &program, x.clone()).unwrap_err();
+        assert!(
+            matches!(err, RuntimeErrorKind::InvalidOperand(_, g) if g == got),
+            "~{x:?}: {err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("Operator ~ cannot be applied to {got}")
+        );
+    }
+}
+
+#[test]
+fn not_and_to_bool_are_folded() {
+    for (src, expected) in [
+        ("!0", Value::Boolean(true)),
+        ("!'a'", Value::Boolean(false)),
+        ("!!2.5", Value::Boolean(true)),
+        ("!![]", Value::Boolean(false)),
+        ("~7", Value::Int(-8)),
+        ("~~7", Value::Int(7)),
+        ("1 && 'a'", Value::Boolean(true)),
+        ("0 || null", Value::Boolean(false)),
+    ] {
+        let (registry, program) = compile(src);
+        let debug = format!("{program:?}");
+        assert!(
+            !debug.contains("UnaryOp") && !debug.contains("ToBool"),
+            "{src}: {debug}"
+        );
+        assert_eq!(
+            run_with(&registry, &program, Value::Null).unwrap(),
+            expected,
+            "{src}"
+        );
+    }
+
+    // Around a variable a double negation becomes a single `ToBool`
+    for src in ["!!x", "!!!!x", "!(!x)"] {
+        let (registry, program) = compile(src);
+        let debug = format!("{program:?}");
+        assert!(!debug.contains("UnaryOp"), "{src}: {debug}");
+        assert!(debug.contains("ToBool"), "{src}: {debug}");
+        for (x, truthy) in values() {
+            let ret = run_with(&registry, &program, x.clone()).unwrap();
+            assert_eq!(ret, Value::Boolean(truthy), "{src} with {x:?}");
+        }
+    }
+}
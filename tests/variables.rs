@@ -0,0 +1,153 @@
+use expr::{
+    Column, Context, Expr, Program, Registry, RegistryError, RuntimeError, RuntimeErrorKind, Type,
+    Value,
+};
+
+#[test]
+fn handles_from_another_registry_are_rejected() {
+    let mut other = Registry::empty();
+    other.add_var(b"a", 1).add_var(b"b", 2).add_var(b"c", 3);
+    let foreign = other.var_handle(b"b").unwrap();
+    let out_of_range = other.var_handle(b"c").unwrap();
+
+    // The handle is in range for both registries
+    let mut registry = Registry::empty();
+    registry.add_var(b"a", "alpha").add_var(b"b", "beta");
+    for handle in [foreign, out_of_range] {
+        assert!(matches!(
+            registry.set_var(handle, 3),
+            Err(RegistryError::InvalidHandle(h)) if h == handle
+        ));
+        assert!(matches!(
+            registry.declare_var(handle, Type::INT),
+            Err(RegistryError::InvalidHandle(_))
+        ));
+        assert!(registry.get_var(handle).is_none());
+    }
+    assert_eq!(
+        registry.get_var(b"b").unwrap().value(),
+        Some(&Value::from("beta"))
+    );
+
+    let mut ctx = Context::new(&registry);
+    assert!(matches!(
+        ctx.set_var(foreign, 3),
+        Err(RegistryError::InvalidHandle(h)) if h == foreign
+    ));
+    assert!(ctx.get_var(foreign).is_none());
+
+    let expr = Expr::from_src(b"b").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    let mut out = vec![Value::Null];
+    let err = program
+        .run_batch(&registry, &ctx, &[(foreign, Column::Int(&[3]))], &mut out)
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::Registry(RegistryError::InvalidHandle(h)) if *h == foreign
+    ));
+
+    // A context only runs programs of its own registry
+    let err = program.run(&registry, &Context::new(&other)).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::Registry(RegistryError::ForeignContext)
+    ));
+}
+
+#[test]
+fn handles_set_the_variable_they_name() {
+    let mut registry = Registry::empty();
+    let a = registry.set_var(b"a", 1).unwrap();
+    assert_eq!(registry.set_var(a, 2).unwrap(), a);
+    assert_eq!(registry.var_handle(b"a"), Some(a));
+    assert_eq!(registry.get_var(a).unwrap().value(), Some(&Value::Int(2)));
+
+    // A removed variable keeps its slot
+    registry.remove_var(b"a");
+    assert!(registry.get_var(a).is_none());
+    assert_eq!(registry.set_var(a, 3).unwrap(), a);
+    assert_eq!(
+        registry.get_var(b"a").unwrap().value(),
+        Some(&Value::Int(3))
+    );
+}
+
+#[test]
+fn set_var_keeps_the_declared_type() {
+    let mut registry = Registry::empty();
+    let w = registry.declare_var(b"w", Type::NUMBER).unwrap();
+    assert_eq!(registry.set_var(w, 2.5).unwrap(), w);
+    assert_eq!(registry.set_var(b"w", 3).unwrap(), w);
+    assert_eq!(registry.get_var(w).unwrap().ty(), Type::NUMBER);
+
+    let err = registry.set_var(w, "str").unwrap_err();
+    assert!(matches!(
+        err,
+        RegistryError::TypeMismatch { ref name, expected, got }
+            if name == "w" && expected == Type::NUMBER && got == Type::STRING
+    ));
+    assert_eq!(registry.get_var(w).unwrap().value(), Some(&Value::Int(3)));
+
+    // `add_var` declares the variable again
+    registry.add_var(b"w", "str");
+    assert_eq!(registry.get_var(w).unwrap().ty(), Type::STRING);
+}
+
+#[test]
+fn strict_types_follow_the_declared_type() {
+    let mut registry = Registry::default();
+    registry.set_strict_types(true);
+    let w = registry.declare_var(b"w", Type::NUMBER).unwrap();
+    registry.set_var(w, 1).unwrap();
+    assert!(registry.set_var(w, "str").is_err());
+
+    let expr = Expr::from_src(b"w * 2").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    assert_eq!(
+        program.run(&registry, &Context::new(&registry)).unwrap(),
+        Value::Int(2)
+    );
+}
+
+fn type_mismatch(err: &RuntimeError) -> (&str, Type, Type) {
+    match err.kind() {
+        RuntimeErrorKind::Registry(RegistryError::TypeMismatch {
+            name,
+            expected,
+            got,
+        }) => (name, *expected, *got),
+        kind => panic!("expected a type mismatch but got {kind:?}"),
+    }
+}
+
+#[test]
+fn context_values_must_fit_the_declared_type() {
+    let mut registry = Registry::default();
+    registry.set_strict_types(true);
+    let n = registry.declare_var(b"n", Type::INT).unwrap();
+
+    let expr = Expr::from_src(b"n + 1").unwrap();
+    let program = Program::compile(&registry, &expr).unwrap();
+    let mut ctx = Context::new(&registry);
+    ctx.set_var(n, 41).unwrap();
+    assert_eq!(program.run(&registry, &ctx).unwrap(), Value::Int(42));
+
+    ctx.set_var(n, "str").unwrap();
+    let err = program.run(&registry, &ctx).unwrap_err();
+    assert_eq!(type_mismatch(&err), ("n", Type::INT, Type::STRING));
+
+    let mut out = vec![Value::Null; 2];
+    let values = [Value::Int(1), Value::from("str")];
+    for column in [Column::Float(&[1.0, 2.0]), Column::Value(&values)] {
+        let err = program
+            .run_batch(
+                &registry,
+                &Context::new(&registry),
+                &[(n, column)],
+                &mut out,
+            )
+            .unwrap_err();
+        assert_eq!(type_mismatch(&err).0, "n");
+    }
+}